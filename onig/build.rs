@@ -0,0 +1,12 @@
+use std::env;
+
+/// Sets `cfg(onig_bundled)` when `onig_sys` compiled its bundled copy
+/// of Oniguruma rather than linking a system library. Tests which
+/// depend on how the bundled copy is configured are skipped otherwise.
+pub fn main() {
+    println!("cargo:rerun-if-env-changed=DEP_ONIG_BUNDLED");
+    println!("cargo:rustc-check-cfg=cfg(onig_bundled)");
+    if env::var_os("DEP_ONIG_BUNDLED").is_some() {
+        println!("cargo:rustc-cfg=onig_bundled");
+    }
+}
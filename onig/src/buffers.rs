@@ -4,6 +4,8 @@
 //! Rust strings into oniguruma char buffers to search and compile
 //! with.
 
+use super::{Encoding, Error, ErrorKind};
+
 /// Encoded String Buffer
 ///
 /// Represents a buffer of characters with encoding information
@@ -23,7 +25,7 @@ pub trait EncodedChars {
 
     /// The encoding of the contents of the buffer
     fn encoding(&self) -> onig_sys::OnigEncoding {
-        unsafe { &mut onig_sys::OnigEncodingUTF8 }
+        Encoding::Utf8.as_raw()
     }
 
     /// The length of this buffer
@@ -63,8 +65,57 @@ pub struct EncodedBytes<'a> {
 }
 
 impl<'a> EncodedBytes<'a> {
+    /// New Buffer
+    ///
+    /// # Arguments
+    ///
+    ///  * `bytes` - The contents of the buffer
+    ///  * `encoding` - The encoding this buffer is in
+    ///
+    /// # Returns
+    ///
+    /// A new buffer instance
+    pub fn new(bytes: &'a [u8], encoding: Encoding) -> EncodedBytes<'a> {
+        EncodedBytes::from_parts(bytes, encoding.as_raw())
+    }
+
+    /// New Validated Buffer
+    ///
+    /// Checks that `bytes` is a valid string in `encoding` before
+    /// creating the buffer. Malformed multibyte input can make
+    /// Oniguruma step over character boundaries while searching, so
+    /// use this rather than `EncodedBytes::new` for untrusted input.
+    ///
+    /// # Arguments
+    ///
+    ///  * `bytes` - The contents of the buffer
+    ///  * `encoding` - The encoding this buffer is in
+    ///
+    /// # Returns
+    ///
+    /// A new buffer instance, or an `Error` with the kind
+    /// `ErrorKind::InvalidEncoding` if `bytes` isn't valid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{EncodedBytes, Encoding};
+    ///
+    /// assert!(EncodedBytes::try_new("héllo".as_bytes(), Encoding::Utf8).is_ok());
+    /// assert!(EncodedBytes::try_new(b"h\xe9llo", Encoding::Utf8).is_err());
+    /// ```
+    pub fn try_new(bytes: &'a [u8], encoding: Encoding) -> Result<EncodedBytes<'a>, Error> {
+        let buffer = EncodedBytes::new(bytes, encoding);
+        check_validity(&buffer)?;
+        Ok(buffer)
+    }
+
     /// New Buffer from Parts
     ///
+    /// Prefer `EncodedBytes::new` unless `enc` is an encoding which
+    /// isn't built into Oniguruma. The contents of the buffer aren't
+    /// checked, see `EncodedBytes::try_new`.
+    ///
     /// # Arguments
     ///
     ///  * `bytes` - The contents of the buffer
@@ -89,7 +140,7 @@ impl<'a> EncodedBytes<'a> {
     pub fn ascii(bytes: &'a [u8]) -> EncodedBytes<'a> {
         EncodedBytes {
             bytes,
-            enc: unsafe { &mut onig_sys::OnigEncodingASCII },
+            enc: Encoding::Ascii.as_raw(),
         }
     }
 }
@@ -112,6 +163,28 @@ impl<'a> EncodedChars for EncodedBytes<'a> {
     }
 }
 
+/// Check that the contents of `chars` are valid in its encoding.
+pub(crate) fn check_validity<T>(chars: &T) -> Result<(), Error>
+where
+    T: EncodedChars,
+{
+    let valid = unsafe {
+        onig_sys::onigenc_is_valid_mbc_string(
+            chars.encoding(),
+            chars.start_ptr(),
+            chars.limit_ptr(),
+        )
+    };
+    if valid != 0 {
+        return Ok(());
+    }
+    let message = match Encoding::from_raw(chars.encoding()) {
+        Some(encoding) => format!("Buffer is not valid {}", encoding),
+        None => "Buffer is not valid in its encoding".to_owned(),
+    };
+    Err(Error::custom(ErrorKind::InvalidEncoding, message))
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -160,6 +233,13 @@ pub mod tests {
         );
     }
 
+    #[test]
+    pub fn byte_buffer_create_with_encoding() {
+        let buff = EncodedBytes::new(b"h\x00i\x00", Encoding::Utf16Le);
+        assert_eq!(Encoding::Utf16Le.as_raw(), buff.encoding());
+        assert_eq!(buff.len(), 4);
+    }
+
     #[test]
     pub fn byte_buffer_create() {
         let buff = b"hello world";
@@ -174,4 +254,19 @@ pub mod tests {
             buff.len()
         );
     }
+
+    #[test]
+    pub fn byte_buffer_try_new() {
+        let buff = EncodedBytes::try_new("日本語".as_bytes(), Encoding::Utf8).unwrap();
+        assert_eq!(buff.len(), 9);
+        assert!(EncodedBytes::try_new(b"\xff\xfe", Encoding::Ascii).is_ok());
+
+        let err = EncodedBytes::try_new(b"\xe6\x97", Encoding::Utf8)
+            .err()
+            .unwrap();
+        assert!(err.is_invalid_encoding());
+        assert_eq!(err.description(), "Buffer is not valid UTF-8");
+        assert!(EncodedBytes::try_new(b"\x80abc", Encoding::Utf8).is_err());
+        assert!(EncodedBytes::try_new(b"\x82", Encoding::ShiftJis).is_err());
+    }
 }
@@ -0,0 +1,321 @@
+//! Regex Builder
+//!
+//! Contains the definition for the `RegexBuilder` struct. This can be
+//! used to configure how a pattern is compiled before creating the
+//! `Regex`.
+
+use std::os::raw::c_int;
+use std::ptr::null_mut;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+use super::{
+    defaults, CaseFold, EncodedBytes, EncodedChars, Encoding, Error, Regex, RegexOptions, Syntax,
+    REGEX_NEW_MUTEX,
+};
+
+/// The process-wide capture number limit.
+///
+/// Oniguruma doesn't provide a way to read this limit back, so we
+/// keep track of it here. Only modified with `REGEX_NEW_MUTEX` held.
+pub(crate) static CAPTURE_NUM_LIMIT: AtomicI32 =
+    AtomicI32::new(onig_sys::ONIG_MAX_CAPTURE_NUM as i32);
+
+/// Regex Builder
+///
+/// Collects the options used to compile a pattern into a `Regex`.
+/// Each setter returns the builder so calls can be chained, and
+/// `build` can be called repeatedly to create more than one regex
+/// from the same configuration. Patterns are compiled with
+/// [`onig_new_deluxe`][new_deluxe].
+///
+/// [new_deluxe]: ./onig_sys/fn.onig_new_deluxe.html
+///
+/// # Examples
+///
+/// ```
+/// use onig::{RegexBuilder, RegexOptions, Syntax};
+///
+/// let r = RegexBuilder::new(r"hello\s+(\w+)")
+///     .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+///     .syntax(Syntax::perl_ng())
+///     .build()
+///     .unwrap();
+/// assert!(r.is_match("HELLO world"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexBuilder<'s> {
+    pattern: Vec<u8>,
+    encoding: onig_sys::OnigEncoding,
+    options: RegexOptions,
+    syntax: Option<&'s Syntax>,
+    case_fold_flag: Option<CaseFold>,
+    capture_limit: Option<usize>,
+    parse_depth_limit: Option<u32>,
+}
+
+unsafe impl<'s> Send for RegexBuilder<'s> {}
+unsafe impl<'s> Sync for RegexBuilder<'s> {}
+
+impl<'s> RegexBuilder<'s> {
+    /// Create a Regex Builder
+    ///
+    /// Creates a new builder for the given UTF-8 `pattern`. Until
+    /// they are changed the builder uses the default options and
+    /// syntax.
+    pub fn new(pattern: &str) -> Self {
+        RegexBuilder::with_encoding(pattern)
+    }
+
+    /// Create a Regex Builder, Specifying an Encoding
+    ///
+    /// Creates a new builder for the given `pattern`. The encoding is
+    /// inferred from the `pattern` buffer.
+    pub fn with_encoding<T>(pattern: T) -> Self
+    where
+        T: EncodedChars,
+    {
+        let bytes = unsafe { std::slice::from_raw_parts(pattern.start_ptr(), pattern.len()) };
+        RegexBuilder {
+            pattern: bytes.to_vec(),
+            encoding: pattern.encoding(),
+            options: RegexOptions::REGEX_OPTION_NONE,
+            syntax: None,
+            case_fold_flag: None,
+            capture_limit: None,
+            parse_depth_limit: None,
+        }
+    }
+
+    /// Set the regex compilation options.
+    pub fn options(&mut self, options: RegexOptions) -> &mut Self {
+        self.options = options;
+        self
+    }
+
+    /// Set the syntax which the pattern is written in.
+    ///
+    /// By default the process-wide default from
+    /// `onig::Syntax::default` is used.
+    pub fn syntax(&mut self, syntax: &'s Syntax) -> &mut Self {
+        self.syntax = Some(syntax);
+        self
+    }
+
+    /// Set the encoding of the pattern.
+    ///
+    /// The pattern's bytes are interpreted using this encoding, and
+    /// the compiled regex can only be used to search buffers with the
+    /// same encoding.
+    pub fn encoding(&mut self, encoding: Encoding) -> &mut Self {
+        self.encoding = encoding.as_raw();
+        self
+    }
+
+    /// Set the case fold flags used for `REGEX_OPTION_IGNORECASE`.
+    ///
+    /// By default the process-wide default from
+    /// `onig::defaults::case_fold_flag` is used.
+    pub fn case_fold_flag(&mut self, case_fold_flag: CaseFold) -> &mut Self {
+        self.case_fold_flag = Some(case_fold_flag);
+        self
+    }
+
+    /// Set the maximum number of capture groups the pattern can
+    /// contain. Compiling a pattern with more groups than this fails.
+    pub fn capture_limit(&mut self, limit: usize) -> &mut Self {
+        self.capture_limit = Some(limit);
+        self
+    }
+
+    /// Set the maximum nesting depth allowed when parsing the
+    /// pattern. Zero means Oniguruma's built in default.
+    pub fn parse_depth_limit(&mut self, limit: u32) -> &mut Self {
+        self.parse_depth_limit = Some(limit);
+        self
+    }
+
+    /// Compile the Regex
+    ///
+    /// Attempts to compile the pattern using the builder's current
+    /// settings. If the regex fails to compile the returned `Error`
+    /// contains more information.
+    pub fn build(&self) -> Result<Regex, Error> {
+        let mut reg: onig_sys::OnigRegex = null_mut();
+        let reg_ptr = &mut reg as *mut onig_sys::OnigRegex;
+
+        // We can use this later to get an error message to pass back
+        // if regex creation fails.
+        let mut error = onig_sys::OnigErrorInfo {
+            enc: null_mut(),
+            par: null_mut(),
+            par_end: null_mut(),
+        };
+
+        // The compiled regex may point into the pattern, so it is
+        // compiled from a copy which the `Regex` owns.
+        let source: Box<[u8]> = self.pattern.clone().into_boxed_slice();
+        let pattern = EncodedBytes::from_parts(&source, self.encoding);
+
+        // Grab a lock to make sure that `onig_new_deluxe` isn't
+        // called by more than one thread at a time. This also
+        // protects the global defaults while they are read and the
+        // global limits while they are overridden.
+        let guard = REGEX_NEW_MUTEX.lock().unwrap();
+        #[cfg(feature = "log")]
+        crate::warnings::ensure_logging();
+
+        // The compiled regex keeps a pointer to its syntax, so it is
+        // given a copy which the `Regex` owns.
+        let syntax = Box::new(match self.syntax {
+            Some(syntax) => *syntax,
+            None => *Syntax::default(),
+        });
+        let case_fold_flag = self
+            .case_fold_flag
+            .unwrap_or_else(defaults::case_fold_flag_unlocked);
+        let mut compile_info = onig_sys::OnigCompileInfo {
+            num_of_elements: 5,
+            pattern_enc: self.encoding,
+            target_enc: self.encoding,
+            syntax: &*syntax as *const Syntax as *mut Syntax as *mut onig_sys::OnigSyntaxType,
+            option: self.options.bits(),
+            case_fold_flag: case_fold_flag.bits(),
+        };
+
+        let err = unsafe {
+            let capture_limit = CAPTURE_NUM_LIMIT.load(Ordering::SeqCst);
+            let parse_depth_limit = onig_sys::onig_get_parse_depth_limit();
+            if let Some(limit) = self.capture_limit {
+                onig_sys::onig_set_capture_num_limit(limit.min(c_int::MAX as usize) as c_int);
+            }
+            if let Some(limit) = self.parse_depth_limit {
+                onig_sys::onig_set_parse_depth_limit(limit);
+            }
+
+            let err = onig_sys::onig_new_deluxe(
+                reg_ptr,
+                pattern.start_ptr(),
+                pattern.limit_ptr(),
+                &mut compile_info,
+                &mut error,
+            );
+
+            if self.capture_limit.is_some() {
+                onig_sys::onig_set_capture_num_limit(capture_limit);
+            }
+            if self.parse_depth_limit.is_some() {
+                onig_sys::onig_set_parse_depth_limit(parse_depth_limit);
+            }
+
+            err
+        };
+        drop(guard);
+
+        if err == onig_sys::ONIG_NORMAL as i32 {
+            Ok(Regex::from_raw(reg, source, syntax))
+        } else {
+            Err(Error::from_code_and_info(err, &error, &pattern))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_builder_defaults() {
+        let regex = RegexBuilder::new("he(l+)o").build().unwrap();
+        assert!(regex.is_match("hello"));
+        assert!(!regex.is_match("HELLO"));
+        assert_eq!(regex.captures_len(), 1);
+    }
+
+    #[test]
+    fn test_builder_options() {
+        let regex = RegexBuilder::new("hello")
+            .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("HeLLo"));
+    }
+
+    #[test]
+    fn test_builder_syntax() {
+        let mut syntax = Syntax::ruby().clone();
+        syntax.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_PLUS_ONE_INF);
+        let regex = RegexBuilder::new("a+").syntax(&syntax).build().unwrap();
+        assert!(regex.is_match("a+"));
+        assert!(!regex.is_match("aa"));
+
+        let regex = RegexBuilder::new(r"\(a\)")
+            .syntax(Syntax::grep())
+            .build()
+            .unwrap();
+        assert_eq!(regex.captures_len(), 1);
+    }
+
+    #[test]
+    fn test_builder_encoding() {
+        let regex = RegexBuilder::with_encoding(EncodedBytes::ascii(b"w.rld"))
+            .build()
+            .unwrap();
+        assert_eq!(regex.encoding(), EncodedBytes::ascii(b"").encoding());
+        assert_eq!(
+            regex.find_with_encoding(EncodedBytes::ascii(b"hello world")),
+            Some((6, 11))
+        );
+
+        let regex = RegexBuilder::new("w.rld")
+            .encoding(Encoding::Ascii)
+            .build()
+            .unwrap();
+        assert_eq!(Encoding::from_raw(regex.encoding()), Some(Encoding::Ascii));
+    }
+
+    #[test]
+    fn test_builder_case_fold_flag() {
+        let regex = RegexBuilder::new("ss")
+            .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+            .build()
+            .unwrap();
+        assert!(regex.is_match("ß"));
+
+        let regex = RegexBuilder::new("ss")
+            .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+            .case_fold_flag(CaseFold::CASE_FOLD_ASCII_ONLY)
+            .build()
+            .unwrap();
+        assert!(!regex.is_match("ß"));
+        assert!(regex.is_match("SS"));
+    }
+
+    #[test]
+    fn test_builder_capture_limit() {
+        let mut builder = RegexBuilder::new("(a)(b)(c)");
+        assert!(builder.build().is_ok());
+        let e = builder.capture_limit(2).build().unwrap_err();
+        assert!(e.is_limit_exceeded());
+
+        // The limit only applies to the builder which set it
+        assert!(Regex::new("(a)(b)(c)").is_ok());
+    }
+
+    #[test]
+    fn test_builder_parse_depth_limit() {
+        let pattern = "((((((((((a))))))))))";
+        let e = RegexBuilder::new(pattern)
+            .parse_depth_limit(4)
+            .build()
+            .unwrap_err();
+        assert!(e.is_limit_exceeded());
+        assert!(Regex::new(pattern).is_ok());
+    }
+
+    #[test]
+    fn test_builder_error_span() {
+        let e = RegexBuilder::new(r"(?<foo>a)\k<bar>").build().unwrap_err();
+        assert!(e.is_undefined_name());
+        assert_eq!(e.pattern_span(), Some((12, 15)));
+    }
+}
@@ -0,0 +1,264 @@
+use super::super::{Error, MatchParam, Region, SearchOptions};
+use super::Regex;
+
+/// Captures represents a group of captured byte slices for a single
+/// match.
+///
+/// See `onig::Captures`; positions are byte indices into the buffer
+/// which was searched.
+pub type Captures<'t> = crate::find::Captures<'t, [u8]>;
+
+/// An iterator over capture groups for a particular match of a regular
+/// expression.
+pub type SubCaptures<'t> = crate::find::SubCaptures<'t, [u8]>;
+
+/// An iterator over the names and values of the capture groups for a
+/// particular match of a regular expression.
+pub type SubCapturesNamed<'t> = crate::find::SubCapturesNamed<'t, [u8]>;
+
+/// An iterator over capture group positions for a particular match of
+/// a regular expression.
+pub type SubCapturesPos<'t> = crate::find::SubCapturesPos<'t, [u8]>;
+
+/// An iterator over all non-overlapping matches for a particular
+/// buffer.
+pub type FindMatches<'r, 't> = crate::find::FindMatches<'r, 't, [u8]>;
+
+/// An iterator over all non-overlapping matches for a particular
+/// buffer, which reports engine errors.
+pub type TryFindMatches<'r, 't> = crate::find::TryFindMatches<'r, 't, [u8]>;
+
+/// An iterator that yields all non-overlapping capture groups matching a
+/// particular regular expression.
+pub type FindCaptures<'r, 't> = crate::find::FindCaptures<'r, 't, [u8]>;
+
+/// Yields all slices delimited by a regular expression match.
+pub type RegexSplits<'r, 't> = crate::find::RegexSplits<'r, 't, [u8]>;
+
+/// Yields at most `N` slices delimited by a regular expression match.
+pub type RegexSplitsN<'r, 't> = crate::find::RegexSplitsN<'r, 't, [u8]>;
+
+impl Regex {
+    /// Returns the capture groups corresponding to the leftmost-first match
+    /// in text. Capture group `0` always corresponds to the entire match.
+    /// If no match is found, then `None` is returned.
+    pub fn captures<'t>(&self, text: &'t [u8]) -> Option<Captures<'t>> {
+        self.inner.captures_in(text)
+    }
+
+    /// Returns the capture groups corresponding to the leftmost-first match
+    /// in text, as `captures` does. If the engine fails the search
+    /// then an `Error` is returned rather than panicking.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::MatchParam;
+    /// # use onig::bytes::Regex;
+    /// let re = Regex::new(br"(\d+)-(\d+)").unwrap();
+    /// let caps = re.try_captures(b"ranges 10-20", &MatchParam::default())
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(caps.at(2), Some(&b"20"[..]));
+    /// ```
+    pub fn try_captures<'t>(
+        &self,
+        text: &'t [u8],
+        match_param: &MatchParam,
+    ) -> Result<Option<Captures<'t>>, Error> {
+        self.inner.try_captures_in(text, match_param)
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in `text`,
+    /// returning the start and end byte indices with respect to `text`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::bytes::Regex;
+    /// let re = Regex::new(br"\d+").unwrap();
+    /// let found = re.find_iter(b"a12\xffb2").collect::<Vec<_>>();
+    /// assert_eq!(found, vec![(1, 3), (5, 6)]);
+    /// ```
+    pub fn find_iter<'r, 't>(&'r self, text: &'t [u8]) -> FindMatches<'r, 't> {
+        self.inner.find_iter_in(text)
+    }
+
+    /// Returns an iterator for each successive non-overlapping match
+    /// in `text`, as `find_iter` does. Each item is a `Result`; if the
+    /// engine fails a search the error is yielded and iteration
+    /// stops.
+    pub fn try_find_iter<'r, 't>(
+        &'r self,
+        text: &'t [u8],
+        match_param: &'r MatchParam,
+    ) -> TryFindMatches<'r, 't> {
+        self.inner.try_find_iter_in(text, match_param)
+    }
+
+    /// Returns an iterator over all the non-overlapping capture groups matched
+    /// in `text`. This is operationally the same as `find_iter` (except it
+    /// yields information about submatches).
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t [u8]) -> FindCaptures<'r, 't> {
+        self.inner.captures_iter_in(text)
+    }
+
+    /// Returns an iterator of slices of `text` delimited by a match
+    /// of the regular expression.
+    /// Namely, each element of the iterator corresponds to bytes that
+    /// *aren't* matched by the regular expression.
+    ///
+    /// This method will *not* copy the text given.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::bytes::Regex;
+    /// let re = Regex::new(b"\x00+").unwrap();
+    /// let fields: Vec<&[u8]> = re.split(b"a\x00b\x00\x00c").collect();
+    /// assert_eq!(fields, vec![&b"a"[..], &b"b"[..], &b"c"[..]]);
+    /// ```
+    pub fn split<'r, 't>(&'r self, text: &'t [u8]) -> RegexSplits<'r, 't> {
+        self.inner.split_in(text)
+    }
+
+    /// Returns an iterator of at most `limit` slices of `text` delimited
+    /// by a match of the regular expression. (A `limit` of `0` will return no
+    /// slices.)
+    /// The remainder of the buffer that is not split will be the last element
+    /// in the iterator.
+    ///
+    /// This method will *not* copy the text given.
+    pub fn splitn<'r, 't>(&'r self, text: &'t [u8], limit: usize) -> RegexSplitsN<'r, 't> {
+        self.inner.splitn_in(text, limit)
+    }
+
+    /// Scan the given slice, capturing into the given region and
+    /// executing a callback for each match.
+    pub fn scan_with_region<F>(
+        &self,
+        to_search: &[u8],
+        region: &mut Region,
+        options: SearchOptions,
+        callback: F,
+    ) -> i32
+    where
+        F: Fn(i32, i32, &Region) -> bool,
+    {
+        self.inner
+            .scan_with_region_in(to_search, region, options, callback)
+    }
+
+    /// Scan a Pattern and Observe Captures
+    ///
+    /// The scan function takes a haystack `to_search` and invokes the
+    /// given `callback` for each capture of this expression.
+    pub fn scan<'t, CB>(&self, to_search: &'t [u8], callback: CB)
+    where
+        CB: Fn(i32, Captures<'t>) -> bool,
+    {
+        self.inner.scan_in(to_search, callback)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::MatchParam;
+    use super::super::*;
+
+    #[test]
+    fn test_bytes_captures() {
+        let regex = Regex::new(b"e(l+)|(r+)").unwrap();
+        let captures = regex.captures(b"\xffhello").unwrap();
+        assert_eq!(captures.len(), 3);
+        assert_eq!(captures.pos(0), Some((2, 5)));
+        assert_eq!(captures.at(1), Some(&b"ll"[..]));
+        assert_eq!(captures.at(2), None);
+        assert_eq!(captures.offset(), 2);
+    }
+
+    #[test]
+    fn test_bytes_captures_name() {
+        let regex = Regex::new(br"(?<key>\w+)=(?<value>\w+)|(?<none>x)").unwrap();
+        let captures = regex.captures(b"\xffkey=value").unwrap();
+        assert_eq!(captures.name("key"), Some(&b"key"[..]));
+        assert_eq!(captures.name_pos("value"), Some((5, 10)));
+        assert_eq!(&captures["value"], &b"value"[..]);
+        assert_eq!(captures.name("none"), None);
+        assert_eq!(captures.name("missing"), None);
+        let named = captures.iter_named().collect::<Vec<_>>();
+        assert_eq!(
+            named,
+            vec![
+                (None, Some(&b"key=value"[..])),
+                (Some("key"), Some(&b"key"[..])),
+                (Some("value"), Some(&b"value"[..])),
+                (Some("none"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bytes_subcaptures() {
+        let regex = Regex::new(b"e(l+)").unwrap();
+        let captures = regex.captures(b"hello").unwrap();
+        let caps = captures.iter().collect::<Vec<_>>();
+        assert_eq!(caps, vec![Some(&b"ell"[..]), Some(&b"ll"[..])]);
+        let pos = captures.iter_pos().collect::<Vec<_>>();
+        assert_eq!(pos, vec![Some((1, 4)), Some((2, 4))]);
+    }
+
+    #[test]
+    fn test_bytes_find_iter_many_zero_length() {
+        let re = Regex::new(br"\d*").unwrap();
+        let ms = re.find_iter(b"\xff1bbb2").collect::<Vec<_>>();
+        assert_eq!(ms, vec![(0, 0), (1, 2), (3, 3), (4, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn test_bytes_find_iter_zero_length_multibyte() {
+        let re = Regex::from(super::super::super::Regex::new(r"\d*").unwrap());
+        let ms = re.find_iter("é1".as_bytes()).collect::<Vec<_>>();
+        assert_eq!(ms, vec![(0, 0), (2, 3)]);
+    }
+
+    #[test]
+    fn test_bytes_try_find_iter() {
+        let re = Regex::new(br"\d+").unwrap();
+        let param = MatchParam::default();
+        let ms = re
+            .try_find_iter(b"a12b2", &param)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ms, vec![(1, 3), (4, 5)]);
+    }
+
+    #[test]
+    fn test_bytes_captures_iter() {
+        let re = Regex::new(br"(\w)=(\d)").unwrap();
+        let ms = re
+            .captures_iter(b"a=1\x00b=2")
+            .map(|caps| (caps.at(1).unwrap(), caps.at(2).unwrap()))
+            .collect::<Vec<_>>();
+        assert_eq!(ms, vec![(&b"a"[..], &b"1"[..]), (&b"b"[..], &b"2"[..])]);
+    }
+
+    #[test]
+    fn test_bytes_splitn() {
+        let re = Regex::new(b",").unwrap();
+        let fields = re.splitn(b"a,b,c", 2).collect::<Vec<_>>();
+        assert_eq!(fields, vec![&b"a"[..], &b"b,c"[..]]);
+    }
+
+    #[test]
+    fn test_bytes_scan() {
+        let re = Regex::new(br"\d").unwrap();
+        let count = std::cell::Cell::new(0);
+        re.scan(b"1\xff2\xfe3", |_, caps| {
+            assert_eq!(caps.at(0).map(|m| m.len()), Some(1));
+            count.set(count.get() + 1);
+            true
+        });
+        assert_eq!(count.get(), 3);
+    }
+}
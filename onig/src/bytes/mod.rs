@@ -0,0 +1,304 @@
+//! Byte Oriented Regexes
+//!
+//! This module contains a `Regex` type which searches byte slices
+//! rather than Rust strings. It mirrors the *find* and replace APIs
+//! of the main `onig::Regex` type, but all haystacks are `&[u8]` and
+//! captures yield `&[u8]`.
+//!
+//! By default patterns are compiled with the ASCII encoding, which
+//! treats every byte as a single character. This makes it safe to
+//! search arbitrary binary data. To use a different encoding compile
+//! the regex with `Regex::with_encoding`, or convert an existing
+//! `onig::Regex` with `From`. Haystacks are always searched using the
+//! encoding the regex was compiled with.
+//!
+//! With a multibyte encoding, malformed input can make Oniguruma step
+//! over character boundaries. When searching untrusted data pass
+//! `SEARCH_OPTION_CHECK_VALIDITY_OF_STRING` to `search_with_param`,
+//! which fails with an `InvalidEncoding` error rather than searching
+//! invalid input. `EncodedBytes::try_new` performs the same check up
+//! front.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use onig::bytes::Regex;
+//!
+//! let regex = Regex::new(br"(\w+)=(\d+)").unwrap();
+//! let caps = regex.captures(b"\xff\xfe key=42").unwrap();
+//! assert_eq!(caps.at(1), Some(&b"key"[..]));
+//! assert_eq!(caps.at(2), Some(&b"42"[..]));
+//! ```
+
+mod find;
+mod replace;
+
+pub use self::find::{
+    Captures, FindCaptures, FindMatches, RegexSplits, RegexSplitsN, SubCaptures, SubCapturesNamed,
+    SubCapturesPos, TryFindMatches,
+};
+pub use super::Replacer;
+
+use super::{EncodedBytes, Error, MatchParam, RegexOptions, Region, SearchOptions, Syntax};
+
+/// A Byte Oriented Regex
+///
+/// Wraps a compiled `onig::Regex` so that it can be used to search
+/// byte slices. The haystack is assumed to have the same encoding
+/// as the regex.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Regex {
+    inner: super::Regex,
+}
+
+impl Regex {
+    /// Create a Regex
+    ///
+    /// Compiles `pattern` with the default options and syntax, using
+    /// the ASCII encoding. Bytes outside of the ASCII range are each
+    /// treated as a single character.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::bytes::Regex;
+    /// let r = Regex::new(br"hello (\w+)");
+    /// assert!(r.is_ok());
+    /// ```
+    pub fn new(pattern: &[u8]) -> Result<Regex, Error> {
+        Regex::with_encoding(EncodedBytes::ascii(pattern))
+    }
+
+    /// Create a Regex, Specifying an Encoding
+    ///
+    /// Compiles `pattern` with the default options and syntax. The
+    /// encoding of the regex, and of the haystacks it will search, is
+    /// taken from the `pattern` buffer.
+    pub fn with_encoding(pattern: EncodedBytes<'_>) -> Result<Regex, Error> {
+        Regex::with_options_and_encoding(
+            pattern,
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::default(),
+        )
+    }
+
+    /// Create a Regex, Specifying Options
+    ///
+    /// Compiles `pattern` with the given options and syntax, using the
+    /// ASCII encoding.
+    pub fn with_options(
+        pattern: &[u8],
+        option: RegexOptions,
+        syntax: &Syntax,
+    ) -> Result<Regex, Error> {
+        Regex::with_options_and_encoding(EncodedBytes::ascii(pattern), option, syntax)
+    }
+
+    /// Create a Regex, Specifying Options and Encoding
+    ///
+    /// Compiles `pattern` with the given options and syntax. The
+    /// encoding is taken from the `pattern` buffer.
+    pub fn with_options_and_encoding(
+        pattern: EncodedBytes<'_>,
+        option: RegexOptions,
+        syntax: &Syntax,
+    ) -> Result<Regex, Error> {
+        super::Regex::with_options_and_encoding(pattern, option, syntax).map(Regex::from)
+    }
+
+    /// Get the Underlying Regex
+    pub fn as_regex(&self) -> &super::Regex {
+        &self.inner
+    }
+
+    /// Get the Encoding of the Regex
+    ///
+    /// Haystacks searched by this regex are interpreted using this
+    /// encoding.
+    pub fn encoding(&self) -> onig_sys::OnigEncoding {
+        self.inner.encoding()
+    }
+
+    /// Get the Number of Capture Groups in this Pattern
+    pub fn captures_len(&self) -> usize {
+        self.inner.captures_len()
+    }
+
+    /// Returns true if and only if the regex matches the whole of
+    /// `text`.
+    pub fn is_match(&self, text: &[u8]) -> bool {
+        self.match_with_options(text, 0, SearchOptions::SEARCH_OPTION_WHOLE_STRING, None)
+            .map(|r| r == text.len())
+            .unwrap_or(false)
+    }
+
+    /// Find a Match in a Buffer
+    ///
+    /// Returns the start and end byte offsets of the first match of
+    /// the regex within `text`, or `None` if there is no match.
+    pub fn find(&self, text: &[u8]) -> Option<(usize, usize)> {
+        self.inner.find_with_encoding(self.haystack(text))
+    }
+
+    /// Match Buffer
+    ///
+    /// Try to match the regex against `text`, starting at the byte
+    /// offset `at`. See `onig::Regex::match_with_encoding` for more
+    /// information.
+    pub fn match_with_options(
+        &self,
+        text: &[u8],
+        at: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+    ) -> Option<usize> {
+        self.inner
+            .match_with_encoding(self.haystack(text), at, options, region)
+    }
+
+    /// Search pattern in Buffer
+    ///
+    /// Search for the regex within `text` between the byte offsets
+    /// `from` and `to`. See `onig::Regex::search_with_encoding` for
+    /// more information.
+    pub fn search_with_options(
+        &self,
+        text: &[u8],
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+    ) -> Option<usize> {
+        self.inner
+            .search_with_encoding(self.haystack(text), from, to, options, region)
+    }
+
+    /// Search pattern in Buffer with Match Param
+    ///
+    /// Search for the regex within `text` between the byte offsets
+    /// `from` and `to`. Errors from the engine are returned rather
+    /// than causing a panic. See `onig::Regex::search_with_param` for
+    /// more information.
+    pub fn search_with_param(
+        &self,
+        text: &[u8],
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+        match_param: MatchParam,
+    ) -> Result<Option<usize>, Error> {
+        self.search_raw(text, from, to, options, region, &match_param)
+    }
+
+    pub(crate) fn search_raw(
+        &self,
+        text: &[u8],
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+        match_param: &MatchParam,
+    ) -> Result<Option<usize>, Error> {
+        self.inner
+            .search_raw(self.haystack(text), from, to, options, region, match_param)
+    }
+
+    fn haystack<'t>(&self, text: &'t [u8]) -> EncodedBytes<'t> {
+        EncodedBytes::from_parts(text, self.encoding())
+    }
+}
+
+impl From<super::Regex> for Regex {
+    fn from(inner: super::Regex) -> Regex {
+        Regex { inner }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Encoding;
+    use super::*;
+
+    #[test]
+    fn test_bytes_regex_create() {
+        Regex::new(br"a \w+ word").unwrap();
+        Regex::with_options(b".*", RegexOptions::REGEX_OPTION_NONE, Syntax::default()).unwrap();
+        assert!(Regex::new(b"a(b").is_err());
+    }
+
+    #[test]
+    fn test_bytes_regex_is_ascii() {
+        let regex = Regex::new(b".").unwrap();
+        assert_eq!(Encoding::from_raw(regex.encoding()), Some(Encoding::Ascii));
+        assert!(regex.is_match(b"\xff"));
+    }
+
+    #[test]
+    fn test_bytes_regex_is_match() {
+        let regex = Regex::new(b"he(l+)o").unwrap();
+        assert!(regex.is_match(b"hello"));
+        assert!(!regex.is_match(b"hello 2.0"));
+    }
+
+    #[test]
+    fn test_bytes_regex_find() {
+        let regex = Regex::new(b"he(l+)o").unwrap();
+        assert_eq!(regex.find(b"\x80\x81, hello!"), Some((4, 9)));
+        assert_eq!(regex.find(b"hey, honey!"), None);
+    }
+
+    #[test]
+    fn test_bytes_regex_latin1() {
+        let pattern = EncodedBytes::new(b"caf\xe9", Encoding::Iso8859_1);
+        let regex = Regex::with_options_and_encoding(
+            pattern,
+            RegexOptions::REGEX_OPTION_IGNORECASE,
+            Syntax::default(),
+        )
+        .unwrap();
+        assert_eq!(regex.find(b"Un CAF\xc9 noir"), Some((3, 7)));
+    }
+
+    #[test]
+    fn test_bytes_regex_char_len_at_end() {
+        use super::super::find::Haystack;
+        let char_len = |regex: &Regex, text: &[u8], at| text.char_len(regex.as_regex(), at);
+
+        let pattern = EncodedBytes::new(b"", Encoding::Gb18030);
+        let regex = Regex::with_encoding(pattern).unwrap();
+        assert_eq!(char_len(&regex, b"a\x81", 1), 1);
+        assert_eq!(char_len(&regex, b"a\x81\x30", 1), 2);
+        assert_eq!(char_len(&regex, b"a\x81\x30\x81\x30", 1), 4);
+
+        let pattern = EncodedBytes::new(b"", Encoding::Utf32Le);
+        let regex = Regex::with_encoding(pattern).unwrap();
+        assert_eq!(char_len(&regex, b"a\0\0", 0), 3);
+        assert_eq!(char_len(&regex, b"a\0\0\0", 0), 4);
+    }
+
+    #[test]
+    fn test_bytes_regex_from_utf8_regex() {
+        let regex = Regex::from(super::super::Regex::new("é+").unwrap());
+        assert_eq!(regex.find("caféé".as_bytes()), Some((3, 7)));
+    }
+
+    #[test]
+    fn test_bytes_search_with_param() {
+        let regex = Regex::new(b"(a|b|ab)*bc").unwrap();
+        let text = b"abababababababacbc";
+        let mut param = MatchParam::default();
+        param.set_retry_limit_in_match(10);
+        let e = regex
+            .search_with_param(
+                text,
+                0,
+                text.len(),
+                SearchOptions::SEARCH_OPTION_NONE,
+                None,
+                param,
+            )
+            .unwrap_err();
+        assert!(e.is_limit_exceeded());
+    }
+}
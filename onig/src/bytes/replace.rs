@@ -0,0 +1,66 @@
+use super::{Regex, Replacer};
+
+impl Regex {
+    /// Replaces the leftmost-first match with the replacement provided.
+    /// The replacement can be a byte slice or a function that takes
+    /// the matches `Captures` and returns the replaced bytes.
+    ///
+    /// If no match is found, then a copy of the buffer is returned unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// # use onig::bytes::{Captures, Regex};
+    /// let re = Regex::new(br"(\w+)=(\w+)").unwrap();
+    /// let result = re.replace(b"\xffkey=value", |caps: &Captures| {
+    ///     let mut swapped = caps.at(2).unwrap().to_vec();
+    ///     swapped.push(b'=');
+    ///     swapped.extend_from_slice(caps.at(1).unwrap());
+    ///     swapped
+    /// });
+    /// assert_eq!(result, b"\xffvalue=key");
+    /// ```
+    pub fn replace<R: Replacer<[u8]>>(&self, text: &[u8], rep: R) -> Vec<u8> {
+        self.replacen(text, 1, rep)
+    }
+
+    /// Replaces all non-overlapping matches in `text` with the
+    /// replacement provided. This is the same as calling `replacen` with
+    /// `limit` set to `0`.
+    ///
+    /// See the documentation for `replace` for details on how to access
+    /// submatches in the replacement.
+    pub fn replace_all<R: Replacer<[u8]>>(&self, text: &[u8], rep: R) -> Vec<u8> {
+        self.replacen(text, 0, rep)
+    }
+
+    /// Replaces at most `limit` non-overlapping matches in `text` with the
+    /// replacement provided. If `limit` is 0, then all non-overlapping matches
+    /// are replaced.
+    ///
+    /// See the documentation for `replace` for details on how to access
+    /// submatches in the replacement.
+    pub fn replacen<R: Replacer<[u8]>>(&self, text: &[u8], limit: usize, rep: R) -> Vec<u8> {
+        self.inner.replacen_in(text, limit, rep)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_bytes_replace() {
+        let re = Regex::new(b"\xff+").unwrap();
+        assert_eq!(re.replace(b"a\xff\xffb\xffc", &b"-"[..]), b"a-b\xffc");
+        assert_eq!(re.replace_all(b"a\xff\xffb\xffc", &b"-"[..]), b"a-b-c");
+        assert_eq!(re.replacen(b"\xffa\xffb\xffc", 2, &b""[..]), b"ab\xffc");
+    }
+
+    #[test]
+    fn test_bytes_replace_with_closure() {
+        let re = Regex::new(br"\d").unwrap();
+        let result = re.replace_all(b"a1b2", |caps: &Captures| vec![caps.at(0).unwrap()[0] + 1]);
+        assert_eq!(result, b"a2b3");
+    }
+}
@@ -0,0 +1,610 @@
+//! Callouts
+//!
+//! Callouts allow a pattern to call back into Rust code while it is
+//! being matched. This module contains `register_callout`, which
+//! registers a closure under a name so that patterns can invoke it
+//! with `(*name)` or `(*name{arg,...})`, and the `CalloutArgs` view
+//! passed to callouts.
+//!
+//! Callouts of name are only available in syntaxes which support
+//! them, such as `Syntax::oniguruma()` and `Syntax::perl()`. Other
+//! syntaxes, such as `Syntax::ruby()`, don't, but they can be enabled
+//! on a custom syntax with
+//! `SyntaxOperator::SYNTAX_OPERATOR_ASTERISK_CALLOUT_NAME`.
+//!
+//! Oniguruma also has built in callouts which need no registration.
+//! The monitors `(*COUNT[tag])`, `(*TOTAL_COUNT[tag])` and
+//! `(*MAX[tag]{n})` keep counters in the `MatchParam` of a search,
+//! which can be read back afterwards with `Regex::callout_counter`.
+//! `(*CMP{tag,op,tag_or_number})` compares counters during the match.
+
+use std::any::Any;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::os::raw::{c_int, c_long, c_uint, c_void};
+use std::panic::{self, AssertUnwindSafe};
+use std::slice;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+
+use super::{Encoding, Error, MatchParam, Regex, REGEX_NEW_MUTEX};
+
+/// The maximum number of arguments a callout of name can be passed.
+const MAX_ARGS: usize = onig_sys::ONIG_CALLOUT_MAX_ARGS_NUM as usize;
+
+type CalloutFn = dyn Fn(&CalloutArgs) -> CalloutResult + Send + Sync;
+
+/// The address of the encoding a callout was registered for, and
+/// Oniguruma's ID for its name.
+type CalloutKey = (usize, c_int);
+
+/// Registered callouts, keyed by encoding and name.
+static CALLOUTS: Lazy<RwLock<HashMap<CalloutKey, Arc<CalloutFn>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+thread_local! {
+    /// A panic caught in a callout on this thread, waiting to be
+    /// resumed once the search which called it has returned.
+    static CALLOUT_PANIC: Cell<Option<Box<dyn Any + Send>>> = Cell::new(None);
+}
+
+/// Callout Result
+///
+/// Returned from a callout to tell Oniguruma how to continue the
+/// match.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CalloutResult {
+    /// Continue matching
+    Continue,
+    /// Fail at this point and backtrack
+    Fail,
+    /// Abandon the search. The search returns an `Error`.
+    Error,
+}
+
+impl CalloutResult {
+    pub(crate) fn as_raw(self) -> c_int {
+        match self {
+            CalloutResult::Continue => onig_sys::OnigCalloutResult_ONIG_CALLOUT_SUCCESS as c_int,
+            CalloutResult::Fail => onig_sys::OnigCalloutResult_ONIG_CALLOUT_FAIL as c_int,
+            CalloutResult::Error => onig_sys::ONIG_ABORT,
+        }
+    }
+}
+
+/// Callout Arguments
+///
+/// A view of the state of the match at the point a callout was
+/// invoked. Positions are byte offsets into the string being
+/// searched.
+pub struct CalloutArgs<'a> {
+    raw: *mut onig_sys::OnigCalloutArgs,
+    marker: PhantomData<&'a onig_sys::OnigCalloutArgs>,
+}
+
+impl<'a> CalloutArgs<'a> {
+    pub(crate) unsafe fn from_raw(raw: *mut onig_sys::OnigCalloutArgs) -> CalloutArgs<'a> {
+        CalloutArgs {
+            raw,
+            marker: PhantomData,
+        }
+    }
+
+    /// The whole of the string being searched.
+    pub fn string(&self) -> &'a [u8] {
+        unsafe {
+            let start = onig_sys::onig_get_string_by_callout_args(self.raw);
+            let end = onig_sys::onig_get_string_end_by_callout_args(self.raw);
+            slice::from_raw_parts(start, end as usize - start as usize)
+        }
+    }
+
+    /// The offset the current match attempt started at.
+    pub fn start(&self) -> usize {
+        self.offset_of(unsafe { onig_sys::onig_get_start_by_callout_args(self.raw) })
+    }
+
+    /// The offset the matcher has reached.
+    pub fn position(&self) -> usize {
+        self.offset_of(unsafe { onig_sys::onig_get_current_by_callout_args(self.raw) })
+    }
+
+    /// The number of the callout within the pattern. Callouts are
+    /// numbered from 1 in order of appearance.
+    pub fn callout_num(&self) -> usize {
+        unsafe { onig_sys::onig_get_callout_num_by_callout_args(self.raw) as usize }
+    }
+
+    /// The contents of a contents callout, `(?{...})`. Returns `None`
+    /// for callouts of name.
+    pub fn contents(&self) -> Option<&'a [u8]> {
+        unsafe {
+            let start = onig_sys::onig_get_contents_by_callout_args(self.raw);
+            let end = onig_sys::onig_get_contents_end_by_callout_args(self.raw);
+            if start.is_null() || end.is_null() {
+                None
+            } else {
+                Some(slice::from_raw_parts(start, end as usize - start as usize))
+            }
+        }
+    }
+
+    /// Returns true if the callout is being called while the matcher
+    /// backtracks over it, rather than when the matcher reaches it.
+    pub fn is_retraction(&self) -> bool {
+        unsafe {
+            onig_sys::onig_get_callout_in_by_callout_args(self.raw)
+                == onig_sys::OnigCalloutIn_ONIG_CALLOUT_IN_RETRACTION
+        }
+    }
+
+    /// The number of times the matcher has retried so far.
+    pub fn retry_counter(&self) -> u64 {
+        unsafe { onig_sys::onig_get_retry_counter_by_callout_args(self.raw) as u64 }
+    }
+
+    /// Returns the number of capture groups in the pattern, including
+    /// the whole match group `0`.
+    pub fn captures_len(&self) -> usize {
+        unsafe {
+            let reg = onig_sys::onig_get_regex_by_callout_args(self.raw);
+            onig_sys::onig_number_of_captures(reg) as usize + 1
+        }
+    }
+
+    /// Returns the start and end of capture group `group` as it stands
+    /// at this point in the match, or `None` if the group hasn't
+    /// captured anything.
+    pub fn capture_pos(&self, group: usize) -> Option<(usize, usize)> {
+        if group == 0 || group >= self.captures_len() {
+            return None;
+        }
+        let (mut beg, mut end) = (0, 0);
+        let r = unsafe {
+            onig_sys::onig_get_capture_range_in_callout(
+                self.raw,
+                group as c_int,
+                &mut beg,
+                &mut end,
+            )
+        };
+        if r == onig_sys::ONIG_NORMAL as c_int && beg >= 0 && end >= 0 {
+            Some((beg as usize, end as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the text captured by group `group` at this point in the
+    /// match, or `None` if the group hasn't captured anything.
+    pub fn capture(&self, group: usize) -> Option<&'a [u8]> {
+        self.capture_pos(group)
+            .map(|(beg, end)| &self.string()[beg..end])
+    }
+
+    /// The number of arguments passed to the callout in the pattern.
+    pub fn args_len(&self) -> usize {
+        unsafe { onig_sys::onig_get_passed_args_num_by_callout_args(self.raw).max(0) as usize }
+    }
+
+    /// Returns the `index`th argument passed to the callout in the
+    /// pattern, or `None` if there are fewer arguments.
+    pub fn arg(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.args_len() {
+            return None;
+        }
+        let mut ty: onig_sys::OnigType = onig_sys::OnigType_ONIG_TYPE_VOID;
+        let mut val = onig_sys::OnigValue { l: 0 };
+        unsafe {
+            let r =
+                onig_sys::onig_get_arg_by_callout_args(self.raw, index as c_int, &mut ty, &mut val);
+            if r != onig_sys::ONIG_NORMAL as c_int || ty != onig_sys::OnigType_ONIG_TYPE_STRING {
+                return None;
+            }
+            let (start, end) = (val.s.start, val.s.end);
+            Some(slice::from_raw_parts(start, end as usize - start as usize))
+        }
+    }
+
+    fn offset_of(&self, ptr: *const onig_sys::OnigUChar) -> usize {
+        let start = unsafe { onig_sys::onig_get_string_by_callout_args(self.raw) };
+        ptr as usize - start as usize
+    }
+}
+
+/// Register a Callout
+///
+/// Registers `callout` under `name` for UTF-8 patterns. Patterns
+/// compiled after this call can invoke the callout with `(*name)`,
+/// or pass it up to four arguments with `(*name{a,b})`. Registering
+/// a name again replaces the previous callout for that encoding.
+///
+/// If the callout panics the search is abandoned, and the panic
+/// carries on from the search method once Oniguruma has returned.
+///
+/// # Examples
+///
+/// ```
+/// use onig::{register_callout, CalloutResult, Regex, RegexOptions, Syntax};
+///
+/// register_callout("even_start", |args| {
+///     if args.start() % 2 == 0 {
+///         CalloutResult::Continue
+///     } else {
+///         CalloutResult::Fail
+///     }
+/// })
+/// .unwrap();
+///
+/// let regex = Regex::with_options(
+///     r"a+(*even_start)b",
+///     RegexOptions::REGEX_OPTION_NONE,
+///     Syntax::oniguruma(),
+/// )
+/// .unwrap();
+/// assert_eq!(regex.find("xaaab"), Some((2, 5)));
+/// ```
+pub fn register_callout<F>(name: &str, callout: F) -> Result<(), Error>
+where
+    F: Fn(&CalloutArgs) -> CalloutResult + Send + Sync + 'static,
+{
+    register_callout_with_encoding(name, Encoding::Utf8, callout)
+}
+
+/// Register a Callout, Specifying an Encoding
+///
+/// Registers `callout` under `name` for patterns in the given
+/// `encoding`. See `register_callout` for more information.
+pub fn register_callout_with_encoding<F>(
+    name: &str,
+    encoding: Encoding,
+    callout: F,
+) -> Result<(), Error>
+where
+    F: Fn(&CalloutArgs) -> CalloutResult + Send + Sync + 'static,
+{
+    // Every callout takes up to the maximum number of string
+    // arguments. All of them are optional, defaulting to empty.
+    let mut arg_types = [onig_sys::OnigType_ONIG_TYPE_STRING as c_uint; MAX_ARGS];
+    let empty = b"".as_ptr() as *mut onig_sys::OnigUChar;
+    let mut defaults = [onig_sys::OnigValue {
+        s: onig_sys::OnigValue__bindgen_ty_1 {
+            start: empty,
+            end: empty,
+        },
+    }; MAX_ARGS];
+
+    let mut callouts = CALLOUTS.write().unwrap();
+    let id = unsafe {
+        let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+        onig_sys::onig_set_callout_of_name(
+            encoding.as_raw(),
+            onig_sys::OnigCalloutType_ONIG_CALLOUT_TYPE_SINGLE,
+            name.as_ptr() as *mut onig_sys::OnigUChar,
+            name[name.len()..].as_ptr() as *mut onig_sys::OnigUChar,
+            onig_sys::OnigCalloutIn_ONIG_CALLOUT_IN_PROGRESS as c_int,
+            Some(callout_trampoline),
+            None,
+            MAX_ARGS as c_int,
+            arg_types.as_mut_ptr(),
+            MAX_ARGS as c_int,
+            defaults.as_mut_ptr(),
+        )
+    };
+    if id < 0 {
+        return Err(Error::from_code(id));
+    }
+    callouts.insert((encoding.as_raw() as usize, id), Arc::new(callout));
+    Ok(())
+}
+
+/// Counter Values of a Built In Callout
+///
+/// The values recorded for a tag by the built in `COUNT`,
+/// `TOTAL_COUNT` and `MAX` callouts. Counters which the callout never
+/// set are reported as zero.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Hash)]
+pub struct CalloutCounter {
+    /// The value of the counter. For `COUNT` and `TOTAL_COUNT` this
+    /// depends on the direction given in the pattern, `{>}` (the
+    /// default) counts progress, `{<}` counts retraction and `{X}`
+    /// counts progress less retraction.
+    pub count: i64,
+    /// The number of times the callout was reached while matching
+    /// forwards.
+    pub progress: i64,
+    /// The number of times the callout was backtracked over.
+    pub retraction: i64,
+}
+
+impl Regex {
+    /// Read the Counters of a Built In Callout
+    ///
+    /// After a search with `param` returns the counters recorded by
+    /// the built in callout tagged `tag` in this regex. `COUNT` and
+    /// `MAX` counters are reset for each match attempt, so reflect the
+    /// final attempt the callout was reached in. `TOTAL_COUNT`
+    /// counters accumulate over the whole search.
+    ///
+    /// Returns an error if the regex has no callout with that tag.
+    ///
+    /// # Examples
+    ///
+    /// Profile which branches of an alternation are taken:
+    ///
+    /// ```
+    /// use onig::{MatchParam, Regex, RegexOptions, Syntax};
+    ///
+    /// let regex = Regex::with_options(
+    ///     r"(?:\d+(*TOTAL_COUNT[num])|\w+(*TOTAL_COUNT[word])|\s+)*",
+    ///     RegexOptions::REGEX_OPTION_NONE,
+    ///     Syntax::oniguruma(),
+    /// )
+    /// .unwrap();
+    ///
+    /// let param = MatchParam::default();
+    /// regex.try_search("one 2 three 4 5", &param).unwrap();
+    /// assert_eq!(regex.callout_counter(&param, "num").unwrap().progress, 3);
+    /// assert_eq!(regex.callout_counter(&param, "word").unwrap().progress, 2);
+    /// ```
+    pub fn callout_counter(&self, param: &MatchParam, tag: &str) -> Result<CalloutCounter, Error> {
+        Ok(CalloutCounter {
+            count: self.callout_data_by_tag(param, tag, 0)?,
+            progress: self.callout_data_by_tag(param, tag, 1)?,
+            retraction: self.callout_data_by_tag(param, tag, 2)?,
+        })
+    }
+
+    /// Read a single counter slot of a tagged callout.
+    fn callout_data_by_tag(
+        &self,
+        param: &MatchParam,
+        tag: &str,
+        slot: c_int,
+    ) -> Result<i64, Error> {
+        let mut data_type: onig_sys::OnigType = onig_sys::OnigType_ONIG_TYPE_VOID;
+        let mut value = onig_sys::OnigValue { l: 0 };
+        let r = unsafe {
+            onig_sys::onig_get_callout_data_by_tag_dont_clear_old(
+                self.as_raw(),
+                param.as_raw(),
+                tag.as_ptr(),
+                tag.as_ptr().add(tag.len()),
+                slot,
+                &mut data_type,
+                &mut value,
+            )
+        };
+        if r < 0 {
+            return Err(Error::from_code(r));
+        }
+        if data_type == onig_sys::OnigType_ONIG_TYPE_LONG {
+            let count: c_long = unsafe { value.l };
+            Ok(count as i64)
+        } else {
+            Ok(0)
+        }
+    }
+}
+
+/// Dispatches callouts of name to the registered Rust closure.
+unsafe extern "C" fn callout_trampoline(
+    args: *mut onig_sys::OnigCalloutArgs,
+    user_data: *mut c_void,
+) -> c_int {
+    if crate::match_param::abort_requested(user_data) {
+        return onig_sys::ONIG_ABORT;
+    }
+    let id = onig_sys::onig_get_name_id_by_callout_args(args);
+    let encoding = onig_sys::onig_get_encoding(onig_sys::onig_get_regex_by_callout_args(args));
+    let callout = CALLOUTS
+        .read()
+        .unwrap()
+        .get(&(encoding as usize, id))
+        .cloned();
+    match callout {
+        Some(callout) => catch_callout_panic(|| callout(&CalloutArgs::from_raw(args)).as_raw()),
+        None => onig_sys::ONIG_ABORT,
+    }
+}
+
+/// Run a callout closure, catching any panic so that it doesn't
+/// unwind into Oniguruma. A panic aborts the search, and is resumed by
+/// `resume_callout_panic` once the search has returned.
+pub(crate) fn catch_callout_panic<F>(callout: F) -> c_int
+where
+    F: FnOnce() -> c_int,
+{
+    match panic::catch_unwind(AssertUnwindSafe(callout)) {
+        Ok(result) => result,
+        Err(payload) => {
+            CALLOUT_PANIC.with(|slot| slot.set(Some(payload)));
+            onig_sys::ONIG_ABORT
+        }
+    }
+}
+
+/// Resume a panic caught in a callout during the search which has
+/// just returned on this thread, if there was one.
+pub(crate) fn resume_callout_panic() {
+    if let Some(payload) = CALLOUT_PANIC.with(|slot| slot.take()) {
+        panic::resume_unwind(payload);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    fn compile(pattern: &str) -> Regex {
+        Regex::with_options(
+            pattern,
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::oniguruma(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_callout_continue() {
+        static CALLS: AtomicUsize = AtomicUsize::new(0);
+        register_callout("test_continue", |_| {
+            CALLS.fetch_add(1, Ordering::SeqCst);
+            CalloutResult::Continue
+        })
+        .unwrap();
+        let regex = compile("a(*test_continue)b");
+        assert_eq!(regex.find("xab"), Some((1, 3)));
+        assert!(CALLS.load(Ordering::SeqCst) > 0);
+    }
+
+    #[test]
+    fn test_callout_fail() {
+        register_callout("test_fail", |_| CalloutResult::Fail).unwrap();
+        let regex = compile("a(*test_fail)|b");
+        assert_eq!(regex.find("ab"), Some((1, 2)));
+    }
+
+    #[test]
+    fn test_callout_error() {
+        register_callout("test_error", |_| CalloutResult::Error).unwrap();
+        let regex = compile("a(*test_error)");
+        let e = regex.try_search("ab", &MatchParam::default()).unwrap_err();
+        assert_eq!(e.code(), onig_sys::ONIG_ABORT);
+    }
+
+    #[test]
+    fn test_callout_args() {
+        static SEEN: Lazy<Mutex<Vec<(usize, usize, Vec<u8>, Vec<u8>)>>> =
+            Lazy::new(|| Mutex::new(Vec::new()));
+        register_callout("test_args", |args| {
+            SEEN.lock().unwrap().push((
+                args.start(),
+                args.position(),
+                args.capture(1).unwrap_or_default().to_vec(),
+                args.arg(0).unwrap_or_default().to_vec(),
+            ));
+            assert_eq!(args.string(), b"xxabc");
+            assert_eq!(args.args_len(), 2);
+            assert_eq!(args.arg(1), Some(&b"two"[..]));
+            assert_eq!(args.arg(2), None);
+            CalloutResult::Continue
+        })
+        .unwrap();
+        let regex = compile("(ab)(*test_args{one,two})c");
+        assert_eq!(regex.find("xxabc"), Some((2, 5)));
+        assert_eq!(
+            *SEEN.lock().unwrap(),
+            vec![(2, 4, b"ab".to_vec(), b"one".to_vec())]
+        );
+    }
+
+    #[test]
+    fn test_callout_panic() {
+        register_callout("test_panic", |args| {
+            if args.position() == 2 {
+                panic!("callout panicked");
+            }
+            CalloutResult::Continue
+        })
+        .unwrap();
+        let regex = compile("a+(*test_panic)");
+        let payload = std::panic::catch_unwind(|| regex.find("xa")).unwrap_err();
+        assert_eq!(payload.downcast_ref::<&str>(), Some(&"callout panicked"));
+
+        // The panic doesn't leak into later searches on this thread.
+        assert_eq!(regex.find("xaaa"), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_callout_unknown_name() {
+        let e = Regex::with_options(
+            "a(*test_not_registered)",
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::oniguruma(),
+        )
+        .unwrap_err();
+        assert_eq!(e.code(), onig_sys::ONIGERR_UNDEFINED_CALLOUT_NAME);
+    }
+
+    #[test]
+    fn test_callout_per_encoding() {
+        register_callout("test_per_encoding", |_| CalloutResult::Continue).unwrap();
+        register_callout_with_encoding("test_per_encoding", Encoding::Ascii, |_| {
+            CalloutResult::Fail
+        })
+        .unwrap();
+
+        let regex = compile("a(*test_per_encoding)");
+        assert_eq!(regex.find("xa"), Some((1, 2)));
+        let regex = RegexBuilder::new("a(*test_per_encoding)")
+            .encoding(Encoding::Ascii)
+            .syntax(Syntax::oniguruma())
+            .build()
+            .unwrap();
+        assert_eq!(regex.find_with_encoding(EncodedBytes::ascii(b"xa")), None);
+
+        // Registering a name again only replaces the callout for the
+        // same encoding.
+        register_callout("test_per_encoding", |_| CalloutResult::Fail).unwrap();
+        let regex = compile("a(*test_per_encoding)");
+        assert_eq!(regex.find("xa"), None);
+    }
+
+    #[test]
+    fn test_callout_invalid_name() {
+        assert!(register_callout("not a name", |_| CalloutResult::Continue).is_err());
+    }
+
+    #[test]
+    fn test_builtin_count() {
+        let regex = compile("(?:a(*COUNT[a]{X}))*ab");
+        let param = MatchParam::default();
+        assert_eq!(regex.try_search("xaaab", &param).unwrap(), Some((1, 5)));
+        let counter = regex.callout_counter(&param, "a").unwrap();
+        // The loop takes all three `a`s then backtracks over one.
+        assert_eq!(
+            counter,
+            CalloutCounter {
+                count: 2,
+                progress: 3,
+                retraction: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_builtin_max_and_cmp() {
+        let regex = compile("(?:a(*MAX{2}))*");
+        assert_eq!(regex.find("aaaa"), Some((0, 2)));
+
+        let regex = compile("(?:a(*COUNT[a]))*(?:b(*COUNT[b]))*(*CMP{a,==,b})c");
+        assert_eq!(regex.find("aabbc"), Some((0, 5)));
+        assert_eq!(regex.find("aabc"), Some((1, 4)));
+    }
+
+    #[test]
+    fn test_callout_counter_unknown_tag() {
+        let regex = compile("a(*COUNT[a])");
+        let param = MatchParam::default();
+        regex.try_search("a", &param).unwrap();
+        assert!(regex.callout_counter(&param, "b").is_err());
+    }
+
+    #[test]
+    fn test_callout_name_operator() {
+        let mut syntax = Syntax::ruby().clone();
+        assert!(
+            Regex::with_options("a(*FAIL)|b", RegexOptions::REGEX_OPTION_NONE, &syntax)
+                .map_or(true, |r| r.find("ab") != Some((1, 2)))
+        );
+        syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_ASTERISK_CALLOUT_NAME);
+        let regex =
+            Regex::with_options("a(*FAIL)|b", RegexOptions::REGEX_OPTION_NONE, &syntax).unwrap();
+        assert_eq!(regex.find("ab"), Some((1, 2)));
+    }
+}
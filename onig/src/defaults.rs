@@ -0,0 +1,166 @@
+//! Process-wide Defaults
+//!
+//! Oniguruma keeps a default syntax, encoding and case fold flag which
+//! are used when a regex doesn't specify its own. `Regex::new` and
+//! `RegexBuilder` use the default syntax and case fold flag, so
+//! changing them changes how later patterns are compiled. Regexes
+//! which have already been compiled aren't affected.
+//!
+//! Getters and setters take the same lock as regex compilation, so they
+//! never race with a regex being built. The defaults are shared by the
+//! whole process, so they are best configured once at startup.
+//! `save` can be used to restore them afterwards, for example at the
+//! end of a test.
+//!
+//! # Examples
+//!
+//! ```
+//! use onig::{defaults, Regex, Syntax};
+//!
+//! {
+//!     let _saved = defaults::save();
+//!     defaults::set_syntax(Syntax::perl_ng());
+//!
+//!     let regex = Regex::new(r"(?<word>\w+)$").unwrap();
+//!     assert_eq!(regex.syntax().name(), Some("perl_ng"));
+//! }
+//!
+//! assert_ne!(Syntax::default().name(), Some("perl_ng"));
+//! ```
+
+use super::{CaseFold, Encoding, Syntax, REGEX_NEW_MUTEX};
+
+/// Get the Default Syntax
+///
+/// The syntax used by `Regex::new` and `RegexBuilder` unless another
+/// is given. This is the same as `Syntax::default()`, but read with
+/// the compile lock held.
+pub fn syntax() -> &'static Syntax {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    Syntax::default()
+}
+
+/// Set the Default Syntax
+///
+/// Sets the syntax used to compile later regexes which don't specify
+/// one. Both the built in syntaxes and custom syntaxes with a
+/// `'static` lifetime can be used.
+pub fn set_syntax(syntax: &'static Syntax) {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    unsafe { set_syntax_unlocked(syntax) }
+}
+
+/// Get the Default Encoding
+///
+/// Returns the encoding Oniguruma uses when none is specified, or
+/// `None` if it has been set to an encoding which isn't built into
+/// Oniguruma.
+pub fn encoding() -> Option<Encoding> {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    Encoding::from_raw(unsafe { onig_sys::onigenc_get_default_encoding() })
+}
+
+/// Set the Default Encoding
+///
+/// Sets the encoding Oniguruma uses when none is specified. Patterns
+/// and haystacks in this crate always carry their own encoding, so
+/// this only affects code which uses `onig_sys` directly.
+///
+/// # Examples
+///
+/// ```
+/// use onig::{defaults, Encoding};
+///
+/// let original = defaults::encoding();
+/// {
+///     let _saved = defaults::save();
+///     defaults::set_encoding(Encoding::ShiftJis);
+///     assert_eq!(defaults::encoding(), Some(Encoding::ShiftJis));
+/// }
+/// assert_eq!(defaults::encoding(), original);
+/// ```
+pub fn set_encoding(encoding: Encoding) {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    unsafe { onig_sys::onigenc_set_default_encoding(encoding.as_raw()) };
+}
+
+/// Get the Default Case Fold Flag
+///
+/// The case folding used for case insensitive matching by regexes
+/// which don't set their own with `RegexBuilder::case_fold_flag`.
+pub fn case_fold_flag() -> CaseFold {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    case_fold_flag_unlocked()
+}
+
+/// Read the default case fold flag without taking the compile lock,
+/// for callers which already hold it.
+pub(crate) fn case_fold_flag_unlocked() -> CaseFold {
+    CaseFold::from_bits_truncate(unsafe { onig_sys::onig_get_default_case_fold_flag() })
+}
+
+/// Set the Default Case Fold Flag
+///
+/// Sets the case folding used by later regexes which don't set their
+/// own.
+///
+/// # Examples
+///
+/// ```
+/// use onig::{defaults, CaseFold, Regex, RegexOptions, Syntax};
+///
+/// let _saved = defaults::save();
+/// defaults::set_case_fold_flag(CaseFold::CASE_FOLD_ASCII_ONLY);
+///
+/// let regex = Regex::with_options("é", RegexOptions::REGEX_OPTION_IGNORECASE, Syntax::default())
+///     .unwrap();
+/// assert!(!regex.is_match("É"));
+/// ```
+pub fn set_case_fold_flag(case_fold_flag: CaseFold) {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    unsafe { onig_sys::onig_set_default_case_fold_flag(case_fold_flag.bits()) };
+}
+
+/// Save the Defaults
+///
+/// Takes a copy of the current defaults. They are restored when the
+/// returned value is dropped.
+pub fn save() -> SavedDefaults {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    SavedDefaults {
+        syntax: Syntax::default(),
+        encoding: unsafe { onig_sys::onigenc_get_default_encoding() },
+        case_fold_flag: unsafe { onig_sys::onig_get_default_case_fold_flag() },
+    }
+}
+
+/// Saved Defaults
+///
+/// A copy of the process-wide defaults, created with `save`. The
+/// defaults are put back when this is dropped.
+#[must_use = "the defaults are restored as soon as this is dropped"]
+#[derive(Debug)]
+pub struct SavedDefaults {
+    syntax: &'static Syntax,
+    encoding: onig_sys::OnigEncoding,
+    case_fold_flag: onig_sys::OnigCaseFoldType,
+}
+
+impl Drop for SavedDefaults {
+    fn drop(&mut self) {
+        let _guard = REGEX_NEW_MUTEX.lock().unwrap_or_else(|e| e.into_inner());
+        unsafe {
+            set_syntax_unlocked(self.syntax);
+            onig_sys::onigenc_set_default_encoding(self.encoding);
+            onig_sys::onig_set_default_case_fold_flag(self.case_fold_flag);
+        }
+    }
+}
+
+unsafe fn set_syntax_unlocked(syntax: &'static Syntax) {
+    // Oniguruma never modifies the default syntax through this
+    // pointer.
+    onig_sys::onig_set_default_syntax(
+        syntax as *const Syntax as *const onig_sys::OnigSyntaxType as *mut _,
+    );
+}
@@ -0,0 +1,342 @@
+//! Character Encodings
+//!
+//! This module contains the `Encoding` enum, a safe handle to each of
+//! the character encodings built into Oniguruma.
+
+use std::ffi::CStr;
+use std::fmt;
+use std::str::FromStr;
+
+use super::{Error, ErrorKind};
+
+/// Character Encoding
+///
+/// Identifies one of the character encodings which ship with
+/// Oniguruma. Patterns and the buffers they search must share the
+/// same encoding.
+///
+/// # Examples
+///
+/// ```
+/// use onig::Encoding;
+///
+/// let enc: Encoding = "shift_jis".parse().unwrap();
+/// assert_eq!(enc, Encoding::ShiftJis);
+/// assert_eq!(enc.name(), "Shift_JIS");
+/// assert_eq!((enc.min_len(), enc.max_len()), (1, 2));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum Encoding {
+    /// US-ASCII. Each byte is a single character.
+    Ascii,
+    /// ISO-8859-1 (Latin-1, Western European)
+    Iso8859_1,
+    /// ISO-8859-2 (Latin-2, Central European)
+    Iso8859_2,
+    /// ISO-8859-3 (Latin-3, South European)
+    Iso8859_3,
+    /// ISO-8859-4 (Latin-4, North European)
+    Iso8859_4,
+    /// ISO-8859-5 (Latin/Cyrillic)
+    Iso8859_5,
+    /// ISO-8859-6 (Latin/Arabic)
+    Iso8859_6,
+    /// ISO-8859-7 (Latin/Greek)
+    Iso8859_7,
+    /// ISO-8859-8 (Latin/Hebrew)
+    Iso8859_8,
+    /// ISO-8859-9 (Latin-5, Turkish)
+    Iso8859_9,
+    /// ISO-8859-10 (Latin-6, Nordic)
+    Iso8859_10,
+    /// ISO-8859-11 (Latin/Thai)
+    Iso8859_11,
+    /// ISO-8859-13 (Latin-7, Baltic Rim)
+    Iso8859_13,
+    /// ISO-8859-14 (Latin-8, Celtic)
+    Iso8859_14,
+    /// ISO-8859-15 (Latin-9)
+    Iso8859_15,
+    /// ISO-8859-16 (Latin-10, South-Eastern European)
+    Iso8859_16,
+    /// UTF-8. The encoding of Rust strings.
+    Utf8,
+    /// UTF-16, big endian
+    Utf16Be,
+    /// UTF-16, little endian
+    Utf16Le,
+    /// UTF-32, big endian
+    Utf32Be,
+    /// UTF-32, little endian
+    Utf32Le,
+    /// EUC-JP (Japanese)
+    EucJp,
+    /// EUC-TW (Traditional Chinese)
+    EucTw,
+    /// EUC-KR (Korean)
+    EucKr,
+    /// EUC-CN (Simplified Chinese)
+    EucCn,
+    /// Shift_JIS (Japanese)
+    ShiftJis,
+    /// KOI8-R (Russian)
+    Koi8R,
+    /// Windows-1251 (Cyrillic)
+    Cp1251,
+    /// Big5 (Traditional Chinese)
+    Big5,
+    /// GB 18030 (Chinese)
+    Gb18030,
+}
+
+impl Encoding {
+    /// Every encoding built into Oniguruma.
+    pub const ALL: &'static [Encoding] = &[
+        Encoding::Ascii,
+        Encoding::Iso8859_1,
+        Encoding::Iso8859_2,
+        Encoding::Iso8859_3,
+        Encoding::Iso8859_4,
+        Encoding::Iso8859_5,
+        Encoding::Iso8859_6,
+        Encoding::Iso8859_7,
+        Encoding::Iso8859_8,
+        Encoding::Iso8859_9,
+        Encoding::Iso8859_10,
+        Encoding::Iso8859_11,
+        Encoding::Iso8859_13,
+        Encoding::Iso8859_14,
+        Encoding::Iso8859_15,
+        Encoding::Iso8859_16,
+        Encoding::Utf8,
+        Encoding::Utf16Be,
+        Encoding::Utf16Le,
+        Encoding::Utf32Be,
+        Encoding::Utf32Le,
+        Encoding::EucJp,
+        Encoding::EucTw,
+        Encoding::EucKr,
+        Encoding::EucCn,
+        Encoding::ShiftJis,
+        Encoding::Koi8R,
+        Encoding::Cp1251,
+        Encoding::Big5,
+        Encoding::Gb18030,
+    ];
+
+    /// Get the Raw Encoding
+    ///
+    /// Returns the `onig_sys` encoding pointer for this encoding.
+    #[allow(unused_unsafe, static_mut_refs)]
+    pub fn as_raw(self) -> onig_sys::OnigEncoding {
+        unsafe {
+            match self {
+                Encoding::Ascii => &mut onig_sys::OnigEncodingASCII as *mut _,
+                Encoding::Iso8859_1 => &mut onig_sys::OnigEncodingISO_8859_1 as *mut _,
+                Encoding::Iso8859_2 => &mut onig_sys::OnigEncodingISO_8859_2 as *mut _,
+                Encoding::Iso8859_3 => &mut onig_sys::OnigEncodingISO_8859_3 as *mut _,
+                Encoding::Iso8859_4 => &mut onig_sys::OnigEncodingISO_8859_4 as *mut _,
+                Encoding::Iso8859_5 => &mut onig_sys::OnigEncodingISO_8859_5 as *mut _,
+                Encoding::Iso8859_6 => &mut onig_sys::OnigEncodingISO_8859_6 as *mut _,
+                Encoding::Iso8859_7 => &mut onig_sys::OnigEncodingISO_8859_7 as *mut _,
+                Encoding::Iso8859_8 => &mut onig_sys::OnigEncodingISO_8859_8 as *mut _,
+                Encoding::Iso8859_9 => &mut onig_sys::OnigEncodingISO_8859_9 as *mut _,
+                Encoding::Iso8859_10 => &mut onig_sys::OnigEncodingISO_8859_10 as *mut _,
+                Encoding::Iso8859_11 => &mut onig_sys::OnigEncodingISO_8859_11 as *mut _,
+                Encoding::Iso8859_13 => &mut onig_sys::OnigEncodingISO_8859_13 as *mut _,
+                Encoding::Iso8859_14 => &mut onig_sys::OnigEncodingISO_8859_14 as *mut _,
+                Encoding::Iso8859_15 => &mut onig_sys::OnigEncodingISO_8859_15 as *mut _,
+                Encoding::Iso8859_16 => &mut onig_sys::OnigEncodingISO_8859_16 as *mut _,
+                Encoding::Utf8 => &mut onig_sys::OnigEncodingUTF8 as *mut _,
+                Encoding::Utf16Be => &mut onig_sys::OnigEncodingUTF16_BE as *mut _,
+                Encoding::Utf16Le => &mut onig_sys::OnigEncodingUTF16_LE as *mut _,
+                Encoding::Utf32Be => &mut onig_sys::OnigEncodingUTF32_BE as *mut _,
+                Encoding::Utf32Le => &mut onig_sys::OnigEncodingUTF32_LE as *mut _,
+                Encoding::EucJp => &mut onig_sys::OnigEncodingEUC_JP as *mut _,
+                Encoding::EucTw => &mut onig_sys::OnigEncodingEUC_TW as *mut _,
+                Encoding::EucKr => &mut onig_sys::OnigEncodingEUC_KR as *mut _,
+                Encoding::EucCn => &mut onig_sys::OnigEncodingEUC_CN as *mut _,
+                Encoding::ShiftJis => &mut onig_sys::OnigEncodingSJIS as *mut _,
+                Encoding::Koi8R => &mut onig_sys::OnigEncodingKOI8_R as *mut _,
+                Encoding::Cp1251 => &mut onig_sys::OnigEncodingCP1251 as *mut _,
+                Encoding::Big5 => &mut onig_sys::OnigEncodingBIG5 as *mut _,
+                Encoding::Gb18030 => &mut onig_sys::OnigEncodingGB18030 as *mut _,
+            }
+        }
+    }
+
+    /// Encoding from Raw Pointer
+    ///
+    /// Looks up the `Encoding` for an `onig_sys` encoding pointer.
+    /// Returns `None` if the pointer isn't one of Oniguruma's built in
+    /// encodings.
+    pub fn from_raw(raw: onig_sys::OnigEncoding) -> Option<Encoding> {
+        Encoding::ALL
+            .iter()
+            .copied()
+            .find(|enc| enc.as_raw() == raw)
+    }
+
+    /// Encoding from Name
+    ///
+    /// Looks up an encoding by its Oniguruma name, such as `"UTF-8"`
+    /// or `"EUC-JP"`. The comparison ignores ASCII case.
+    pub fn from_name(name: &str) -> Option<Encoding> {
+        Encoding::ALL
+            .iter()
+            .copied()
+            .find(|enc| enc.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The name Oniguruma uses for this encoding.
+    pub fn name(self) -> &'static str {
+        unsafe { CStr::from_ptr((*self.as_raw()).name) }
+            .to_str()
+            .unwrap_or_default()
+    }
+
+    /// The minimum length of a character, in bytes.
+    pub fn min_len(self) -> usize {
+        unsafe { (*self.as_raw()).min_enc_len as usize }
+    }
+
+    /// The maximum length of a character, in bytes.
+    pub fn max_len(self) -> usize {
+        unsafe { (*self.as_raw()).max_enc_len as usize }
+    }
+
+    /// Returns true if the code points of this encoding are Unicode
+    /// scalar values.
+    pub(crate) fn is_unicode(self) -> bool {
+        matches!(
+            self,
+            Encoding::Ascii
+                | Encoding::Iso8859_1
+                | Encoding::Utf8
+                | Encoding::Utf16Be
+                | Encoding::Utf16Le
+                | Encoding::Utf32Be
+                | Encoding::Utf32Le
+        )
+    }
+}
+
+/// The length of the character at `at` in `text`, in bytes.
+///
+/// `mbc_enc_len` can look at up to `max_enc_len` bytes, so a character
+/// near the end of `text` is measured from a zero padded copy to avoid
+/// reading past the end of the slice.
+pub(crate) fn char_len(encoding: onig_sys::OnigEncoding, text: &[u8], at: usize) -> usize {
+    if at >= text.len() {
+        return 1;
+    }
+    let rest = &text[at..];
+    let mut padded = [0u8; 8];
+    let len = unsafe {
+        let enc = &*encoding;
+        let max_enc_len = enc.max_enc_len.max(1) as usize;
+        let ptr = if rest.len() >= max_enc_len {
+            rest.as_ptr()
+        } else if rest.len() <= padded.len() {
+            padded[..rest.len()].copy_from_slice(rest);
+            padded.as_ptr()
+        } else {
+            return 1;
+        };
+        enc.mbc_enc_len.map_or(1, |f| f(ptr))
+    };
+    (len.max(1) as usize).min(rest.len())
+}
+
+/// The default encoding is UTF-8, the encoding of Rust strings.
+impl Default for Encoding {
+    fn default() -> Encoding {
+        Encoding::Utf8
+    }
+}
+
+impl From<Encoding> for onig_sys::OnigEncoding {
+    fn from(encoding: Encoding) -> onig_sys::OnigEncoding {
+        encoding.as_raw()
+    }
+}
+
+impl FromStr for Encoding {
+    type Err = Error;
+
+    fn from_str(name: &str) -> Result<Encoding, Error> {
+        Encoding::from_name(name).ok_or_else(|| {
+            Error::custom(
+                ErrorKind::InvalidArgument,
+                format!("Unknown encoding name '{}'", name),
+            )
+        })
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_encoding_raw_round_trip() {
+        for &enc in Encoding::ALL {
+            assert_eq!(Encoding::from_raw(enc.as_raw()), Some(enc));
+        }
+        assert_eq!(Encoding::from_raw(std::ptr::null_mut()), None);
+    }
+
+    #[test]
+    fn test_encoding_names() {
+        assert_eq!(Encoding::Utf8.name(), "UTF-8");
+        assert_eq!(Encoding::Utf16Le.name(), "UTF-16LE");
+        assert_eq!(Encoding::Ascii.name(), "US-ASCII");
+        assert_eq!(Encoding::Iso8859_15.to_string(), "ISO-8859-15");
+        for &enc in Encoding::ALL {
+            assert_eq!(Encoding::from_name(enc.name()), Some(enc));
+        }
+    }
+
+    #[test]
+    fn test_encoding_from_str() {
+        assert_eq!("utf-32be".parse::<Encoding>().unwrap(), Encoding::Utf32Be);
+        assert_eq!("EUC-JP".parse::<Encoding>().unwrap(), Encoding::EucJp);
+        let e = "EBCDIC".parse::<Encoding>().unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    fn test_encoding_lengths() {
+        assert_eq!(
+            (Encoding::Ascii.min_len(), Encoding::Ascii.max_len()),
+            (1, 1)
+        );
+        assert_eq!((Encoding::Utf8.min_len(), Encoding::Utf8.max_len()), (1, 4));
+        assert_eq!(
+            (Encoding::Utf16Be.min_len(), Encoding::Utf16Be.max_len()),
+            (2, 4)
+        );
+        assert_eq!(
+            (Encoding::Utf32Le.min_len(), Encoding::Utf32Le.max_len()),
+            (4, 4)
+        );
+    }
+
+    #[test]
+    fn test_encoding_of_regex() {
+        let regex = Regex::new("a").unwrap();
+        assert_eq!(Encoding::from_raw(regex.encoding()), Some(Encoding::Utf8));
+        let regex = Regex::with_encoding(EncodedBytes::new(b"a", Encoding::Iso8859_1)).unwrap();
+        assert_eq!(
+            Encoding::from_raw(regex.encoding()),
+            Some(Encoding::Iso8859_1)
+        );
+    }
+}
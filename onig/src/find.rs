@@ -1,24 +1,114 @@
-use super::{Regex, Region, SearchOptions};
+use super::{EncodedBytes, Encoding, Error, MatchParam, RawRegex, Regex, Region, SearchOptions};
 use std::iter::FusedIterator;
+use std::ops::Index;
+use std::sync::Arc;
+
+/// A Searchable Buffer
+///
+/// Implemented for `str` and `[u8]` so that the captures and
+/// iterators are shared between `onig::Regex` and
+/// `onig::bytes::Regex`. Positions are always byte offsets.
+pub trait Haystack: ToOwned {
+    /// The length of the buffer in bytes.
+    fn len(&self) -> usize;
+
+    /// The part of the buffer between the byte offsets `beg` and
+    /// `end`.
+    fn slice(&self, beg: usize, end: usize) -> &Self;
+
+    /// The buffer along with the encoding it is searched with.
+    fn encoded(&self, regex: &Regex) -> EncodedBytes<'_>;
+
+    /// The length in bytes of the character starting at `at`.
+    fn char_len(&self, regex: &Regex, at: usize) -> usize;
+
+    /// An empty owned buffer with room for `capacity` bytes.
+    fn with_capacity(capacity: usize) -> Self::Owned;
+
+    /// Append the buffer to `buf`.
+    fn push_to(&self, buf: &mut Self::Owned);
+}
+
+impl Haystack for str {
+    fn len(&self) -> usize {
+        str::len(self)
+    }
+
+    fn slice(&self, beg: usize, end: usize) -> &str {
+        &self[beg..end]
+    }
+
+    fn encoded(&self, _: &Regex) -> EncodedBytes<'_> {
+        EncodedBytes::from_parts(self.as_bytes(), Encoding::Utf8.as_raw())
+    }
+
+    fn char_len(&self, _: &Regex, at: usize) -> usize {
+        self[at..].chars().next().map(|c| c.len_utf8()).unwrap_or(1)
+    }
+
+    fn with_capacity(capacity: usize) -> String {
+        String::with_capacity(capacity)
+    }
+
+    fn push_to(&self, buf: &mut String) {
+        buf.push_str(self)
+    }
+}
+
+impl Haystack for [u8] {
+    fn len(&self) -> usize {
+        <[u8]>::len(self)
+    }
+
+    fn slice(&self, beg: usize, end: usize) -> &[u8] {
+        &self[beg..end]
+    }
+
+    fn encoded(&self, regex: &Regex) -> EncodedBytes<'_> {
+        EncodedBytes::from_parts(self, regex.encoding())
+    }
+
+    fn char_len(&self, regex: &Regex, at: usize) -> usize {
+        super::encoding::char_len(regex.encoding(), self, at)
+    }
+
+    fn with_capacity(capacity: usize) -> Vec<u8> {
+        Vec::with_capacity(capacity)
+    }
+
+    fn push_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self)
+    }
+}
 
 impl Regex {
     /// Returns the capture groups corresponding to the leftmost-first match
     /// in text. Capture group `0` always corresponds to the entire match.
     /// If no match is found, then `None` is returned.
     pub fn captures<'t>(&self, text: &'t str) -> Option<Captures<'t>> {
-        let mut region = Region::new();
-        self.search_with_options(
-            text,
-            0,
-            text.len(),
-            SearchOptions::SEARCH_OPTION_NONE,
-            Some(&mut region),
-        )
-        .map(|pos| Captures {
-            text,
-            region,
-            offset: pos,
-        })
+        self.captures_in(text)
+    }
+
+    /// Returns the capture groups corresponding to the leftmost-first match
+    /// in text, as `captures` does. If the engine fails the search
+    /// then an `Error` is returned rather than panicking.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::{Regex, MatchParam};
+    /// let re = Regex::new(r"(\d+)-(\d+)").unwrap();
+    /// let caps = re.try_captures("ranges 10-20", &MatchParam::default())
+    ///     .unwrap()
+    ///     .unwrap();
+    /// assert_eq!(caps.at(2), Some("20"));
+    /// ```
+    pub fn try_captures<'t>(
+        &self,
+        text: &'t str,
+        match_param: &MatchParam,
+    ) -> Result<Option<Captures<'t>>, Error> {
+        self.try_captures_in(text, match_param)
     }
 
     /// Returns an iterator for each successive non-overlapping match in `text`,
@@ -44,13 +134,31 @@ impl Regex {
     /// # }
     /// ```
     pub fn find_iter<'r, 't>(&'r self, text: &'t str) -> FindMatches<'r, 't> {
-        FindMatches {
-            regex: self,
-            region: Region::new(),
-            text,
-            last_end: 0,
-            last_match_end: None,
-        }
+        self.find_iter_in(text)
+    }
+
+    /// Returns an iterator for each successive non-overlapping match
+    /// in `text`, as `find_iter` does. Each item is a `Result`; if the
+    /// engine fails a search the error is yielded and iteration
+    /// stops.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::{Regex, MatchParam};
+    /// let re = Regex::new(r"\d+").unwrap();
+    /// let param = MatchParam::default();
+    /// let found = re.try_find_iter("a12b2", &param)
+    ///     .collect::<Result<Vec<_>, _>>()
+    ///     .unwrap();
+    /// assert_eq!(found, vec![(1, 3), (4, 5)]);
+    /// ```
+    pub fn try_find_iter<'r, 't>(
+        &'r self,
+        text: &'t str,
+        match_param: &'r MatchParam,
+    ) -> TryFindMatches<'r, 't> {
+        self.try_find_iter_in(text, match_param)
     }
 
     /// Returns an iterator over all the non-overlapping capture groups matched
@@ -78,12 +186,7 @@ impl Regex {
     /// # }
     /// ```
     pub fn captures_iter<'r, 't>(&'r self, text: &'t str) -> FindCaptures<'r, 't> {
-        FindCaptures {
-            regex: self,
-            text,
-            last_end: 0,
-            last_match_end: None,
-        }
+        self.captures_iter_in(text)
     }
 
     /// Returns an iterator of substrings of `text` delimited by a match
@@ -106,10 +209,7 @@ impl Regex {
     /// # }
     /// ```
     pub fn split<'r, 't>(&'r self, text: &'t str) -> RegexSplits<'r, 't> {
-        RegexSplits {
-            finder: self.find_iter(text),
-            last: 0,
-        }
+        self.split_in(text)
     }
 
     /// Returns an iterator of at most `limit` substrings of `text` delimited
@@ -135,10 +235,7 @@ impl Regex {
     /// # }
     /// ```
     pub fn splitn<'r, 't>(&'r self, text: &'t str, limit: usize) -> RegexSplitsN<'r, 't> {
-        RegexSplitsN {
-            splits: self.split(text),
-            n: limit,
-        }
+        self.splitn_in(text, limit)
     }
 
     /// Scan the given slice, capturing into the given region and
@@ -148,17 +245,147 @@ impl Regex {
         to_search: &str,
         region: &mut Region,
         options: SearchOptions,
+        callback: F,
+    ) -> i32
+    where
+        F: Fn(i32, i32, &Region) -> bool,
+    {
+        self.scan_with_region_in(to_search, region, options, callback)
+    }
+
+    /// Scan a Pattern and Observe Captures
+    ///
+    /// The scan function takes a haystack `to_search` and invokes the
+    /// given `callback` for each capture of this expression.
+    pub fn scan<'t, CB>(&self, to_search: &'t str, callback: CB)
+    where
+        CB: Fn(i32, Captures<'t>) -> bool,
+    {
+        self.scan_in(to_search, callback)
+    }
+
+    pub(crate) fn captures_in<'t, T>(&self, text: &'t T) -> Option<Captures<'t, T>>
+    where
+        T: Haystack + ?Sized,
+    {
+        let mut region = Region::new();
+        self.search_with_encoding(
+            text.encoded(self),
+            0,
+            text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )
+        .map(|pos| Captures::new(self, text, region, pos))
+    }
+
+    pub(crate) fn try_captures_in<'t, T>(
+        &self,
+        text: &'t T,
+        match_param: &MatchParam,
+    ) -> Result<Option<Captures<'t, T>>, Error>
+    where
+        T: Haystack + ?Sized,
+    {
+        let mut region = Region::new();
+        let r = self.search_raw(
+            text.encoded(self),
+            0,
+            text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+            match_param,
+        )?;
+        Ok(r.map(|pos| Captures::new(self, text, region, pos)))
+    }
+
+    pub(crate) fn find_iter_in<'r, 't, T>(&'r self, text: &'t T) -> FindMatches<'r, 't, T>
+    where
+        T: Haystack + ?Sized,
+    {
+        FindMatches {
+            regex: self,
+            region: Region::new(),
+            text,
+            last_end: 0,
+            last_match_end: None,
+        }
+    }
+
+    pub(crate) fn try_find_iter_in<'r, 't, T>(
+        &'r self,
+        text: &'t T,
+        match_param: &'r MatchParam,
+    ) -> TryFindMatches<'r, 't, T>
+    where
+        T: Haystack + ?Sized,
+    {
+        TryFindMatches {
+            regex: self,
+            match_param,
+            region: Region::new(),
+            text,
+            last_end: 0,
+            last_match_end: None,
+            failed: false,
+        }
+    }
+
+    pub(crate) fn captures_iter_in<'r, 't, T>(&'r self, text: &'t T) -> FindCaptures<'r, 't, T>
+    where
+        T: Haystack + ?Sized,
+    {
+        FindCaptures {
+            regex: self,
+            text,
+            last_end: 0,
+            last_match_end: None,
+        }
+    }
+
+    pub(crate) fn split_in<'r, 't, T>(&'r self, text: &'t T) -> RegexSplits<'r, 't, T>
+    where
+        T: Haystack + ?Sized,
+    {
+        RegexSplits {
+            finder: self.find_iter_in(text),
+            last: 0,
+        }
+    }
+
+    pub(crate) fn splitn_in<'r, 't, T>(
+        &'r self,
+        text: &'t T,
+        limit: usize,
+    ) -> RegexSplitsN<'r, 't, T>
+    where
+        T: Haystack + ?Sized,
+    {
+        RegexSplitsN {
+            splits: self.split_in(text),
+            n: limit,
+        }
+    }
+
+    pub(crate) fn scan_with_region_in<T, F>(
+        &self,
+        to_search: &T,
+        region: &mut Region,
+        options: SearchOptions,
         mut callback: F,
     ) -> i32
     where
+        T: Haystack + ?Sized,
         F: Fn(i32, i32, &Region) -> bool,
     {
+        use super::EncodedChars;
         use onig_sys::{onig_scan, OnigRegion};
         use std::os::raw::{c_int, c_void};
 
-        // Find the bounds of the string we're searching
-        let start = to_search.as_ptr();
-        let end = to_search[to_search.len()..].as_ptr();
+        // Find the bounds of the buffer we're searching
+        let chars = to_search.encoded(self);
+        let start = chars.start_ptr();
+        let end = chars.limit_ptr();
 
         unsafe extern "C" fn scan_cb<F>(
             i: c_int,
@@ -178,9 +405,9 @@ impl Regex {
             }
         }
 
-        unsafe {
+        let r = unsafe {
             onig_scan(
-                self.raw,
+                self.as_raw(),
                 start,
                 end,
                 (&mut region.raw) as *mut ::onig_sys::OnigRegion,
@@ -188,28 +415,23 @@ impl Regex {
                 Some(scan_cb::<F>),
                 &mut callback as *mut F as *mut c_void,
             )
-        }
+        };
+        crate::callout::resume_callout_panic();
+        r
     }
 
-    /// Scan a Pattern and Observe Captures
-    ///
-    /// The scan function takes a haystack `to_search` and invokes the
-    /// given `callback` for each capture of this expression.
-    pub fn scan<'t, CB>(&self, to_search: &'t str, callback: CB)
+    pub(crate) fn scan_in<'t, T, CB>(&self, to_search: &'t T, callback: CB)
     where
-        CB: Fn(i32, Captures<'t>) -> bool,
+        T: Haystack + ?Sized,
+        CB: Fn(i32, Captures<'t, T>) -> bool,
     {
         let mut region = Region::new();
-        self.scan_with_region(
+        self.scan_with_region_in(
             to_search,
             &mut region,
             SearchOptions::SEARCH_OPTION_NONE,
             |n, s, region| {
-                let captures = Captures {
-                    text: to_search,
-                    region: region.clone(),
-                    offset: s as usize,
-                };
+                let captures = Captures::new(self, to_search, region.clone(), s as usize);
                 callback(n, captures)
             },
         );
@@ -222,15 +444,34 @@ impl Regex {
 /// index corresponds to the next capture group in the regex. Positions
 /// returned from a capture group are always byte indices.
 ///
-/// `'t` is the lifetime of the matched text.
+/// `'t` is the lifetime of the matched text. `T` is the type of the
+/// text, either `str` or, for `onig::bytes`, `[u8]`.
 #[derive(Debug)]
-pub struct Captures<'t> {
-    text: &'t str,
+pub struct Captures<'t, T: ?Sized = str> {
+    regex: Arc<RawRegex>,
+    text: &'t T,
     region: Region,
     offset: usize,
 }
 
-impl<'t> Captures<'t> {
+impl<'t, T> Captures<'t, T>
+where
+    T: Haystack + ?Sized,
+{
+    pub(crate) fn new(
+        regex: &Regex,
+        text: &'t T,
+        region: Region,
+        offset: usize,
+    ) -> Captures<'t, T> {
+        Captures {
+            regex: regex.raw.clone(),
+            text,
+            region,
+            offset,
+        }
+    }
+
     /// Returns the start and end positions of the Nth capture group. Returns
     /// `None` if i is not a valid capture group or if the capture group did
     /// not match anything. The positions returned are always byte indices with
@@ -241,8 +482,36 @@ impl<'t> Captures<'t> {
 
     /// Returns the matched string for the capture group `i`. If `i` isn't
     /// a valid capture group or didn't match anything, then `None` is returned.
-    pub fn at(&self, pos: usize) -> Option<&'t str> {
-        self.pos(pos).map(|(beg, end)| &self.text[beg..end])
+    pub fn at(&self, pos: usize) -> Option<&'t T> {
+        self.pos(pos).map(|(beg, end)| self.text.slice(beg, end))
+    }
+
+    /// Returns the start and end positions of the capture group
+    /// called `name`. Returns `None` if there is no group with that
+    /// name or if the group did not match anything. If more than one
+    /// group has the name then the last one which matched is used.
+    pub fn name_pos(&self, name: &str) -> Option<(usize, usize)> {
+        self.regex
+            .backref_number(name, Some(&self.region))
+            .and_then(|group| self.pos(group))
+    }
+
+    /// Returns the matched string for the capture group called `name`.
+    /// If there is no group with that name or it didn't match anything,
+    /// then `None` is returned.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::Regex;
+    /// let re = Regex::new(r"(?<year>\d{4})-(?<month>\d{2})").unwrap();
+    /// let caps = re.captures("Released 2016-07").unwrap();
+    /// assert_eq!(caps.name("year"), Some("2016"));
+    /// assert_eq!(&caps["month"], "07");
+    /// ```
+    pub fn name(&self, name: &str) -> Option<&'t T> {
+        self.name_pos(name)
+            .map(|(beg, end)| self.text.slice(beg, end))
     }
 
     /// Returns the number of captured groups.
@@ -257,36 +526,94 @@ impl<'t> Captures<'t> {
 
     /// Creates an iterator of all the capture groups in order of appearance in
     /// the regular expression.
-    pub fn iter(&'t self) -> SubCaptures<'t> {
+    pub fn iter(&'t self) -> SubCaptures<'t, T> {
         SubCaptures { idx: 0, caps: self }
     }
 
     /// Creates an iterator of all the capture group positions in order of
     /// appearance in the regular expression. Positions are byte indices in
     /// terms of the original string matched.
-    pub fn iter_pos(&'t self) -> SubCapturesPos<'t> {
+    pub fn iter_pos(&'t self) -> SubCapturesPos<'t, T> {
         SubCapturesPos { idx: 0, caps: self }
     }
 
+    /// Creates an iterator of the name and matched string of each
+    /// capture group, in order of appearance in the regular
+    /// expression. Groups without a name have a name of `None`.
+    pub fn iter_named(&'t self) -> SubCapturesNamed<'t, T> {
+        let mut names = vec![None; self.len()];
+        self.regex.foreach_name(|name, groups| {
+            for &group in groups {
+                if let Some(slot) = names.get_mut(group as usize) {
+                    *slot = Some(name);
+                }
+            }
+            true
+        });
+        SubCapturesNamed {
+            names: names.into_iter(),
+            subs: self.iter(),
+        }
+    }
+
     /// Offset of the captures within the given string slice.
     pub fn offset(&self) -> usize {
         self.offset
     }
 }
 
+/// Get a capture group's value by index.
+///
+/// # Panics
+///
+/// If there is no group at the given index, or the group didn't
+/// match anything.
+impl<'t, T> Index<usize> for Captures<'t, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Output = T;
+
+    fn index(&self, i: usize) -> &T {
+        self.at(i)
+            .unwrap_or_else(|| panic!("no group at index '{}'", i))
+    }
+}
+
+/// Get a capture group's value by name.
+///
+/// # Panics
+///
+/// If there is no group with the given name, or the group didn't
+/// match anything.
+impl<'t, 'i, T> Index<&'i str> for Captures<'t, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Output = T;
+
+    fn index(&self, name: &'i str) -> &T {
+        self.name(name)
+            .unwrap_or_else(|| panic!("no group named '{}'", name))
+    }
+}
+
 /// An iterator over capture groups for a particular match of a regular
 /// expression.
 ///
 /// `'t` is the lifetime of the matched text.
-pub struct SubCaptures<'t> {
+pub struct SubCaptures<'t, T: ?Sized = str> {
     idx: usize,
-    caps: &'t Captures<'t>,
+    caps: &'t Captures<'t, T>,
 }
 
-impl<'t> Iterator for SubCaptures<'t> {
-    type Item = Option<&'t str>;
+impl<'t, T> Iterator for SubCaptures<'t, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = Option<&'t T>;
 
-    fn next(&mut self) -> Option<Option<&'t str>> {
+    fn next(&mut self) -> Option<Option<&'t T>> {
         if self.idx < self.caps.len() {
             self.idx += 1;
             Some(self.caps.at(self.idx - 1))
@@ -305,21 +632,52 @@ impl<'t> Iterator for SubCaptures<'t> {
     }
 }
 
-impl<'t> FusedIterator for SubCaptures<'t> {}
+impl<'t, T> FusedIterator for SubCaptures<'t, T> where T: Haystack + ?Sized {}
+
+impl<'t, T> ExactSizeIterator for SubCaptures<'t, T> where T: Haystack + ?Sized {}
+
+/// An iterator over the names and values of the capture groups for a
+/// particular match of a regular expression.
+///
+/// `'t` is the lifetime of the matched text.
+pub struct SubCapturesNamed<'t, T: ?Sized = str> {
+    names: std::vec::IntoIter<Option<&'t str>>,
+    subs: SubCaptures<'t, T>,
+}
+
+impl<'t, T> Iterator for SubCapturesNamed<'t, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = (Option<&'t str>, Option<&'t T>);
+
+    fn next(&mut self) -> Option<(Option<&'t str>, Option<&'t T>)> {
+        Some((self.names.next()?, self.subs.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.subs.size_hint()
+    }
+}
 
-impl<'t> ExactSizeIterator for SubCaptures<'t> {}
+impl<'t, T> FusedIterator for SubCapturesNamed<'t, T> where T: Haystack + ?Sized {}
+
+impl<'t, T> ExactSizeIterator for SubCapturesNamed<'t, T> where T: Haystack + ?Sized {}
 
 /// An iterator over capture group positions for a particular match of
 /// a regular expression.
 ///
 /// Positions are byte indices in terms of the original
 /// string matched. `'t` is the lifetime of the matched text.
-pub struct SubCapturesPos<'t> {
+pub struct SubCapturesPos<'t, T: ?Sized = str> {
     idx: usize,
-    caps: &'t Captures<'t>,
+    caps: &'t Captures<'t, T>,
 }
 
-impl<'t> Iterator for SubCapturesPos<'t> {
+impl<'t, T> Iterator for SubCapturesPos<'t, T>
+where
+    T: Haystack + ?Sized,
+{
     type Item = Option<(usize, usize)>;
 
     fn next(&mut self) -> Option<Option<(usize, usize)>> {
@@ -341,9 +699,9 @@ impl<'t> Iterator for SubCapturesPos<'t> {
     }
 }
 
-impl<'t> FusedIterator for SubCapturesPos<'t> {}
+impl<'t, T> FusedIterator for SubCapturesPos<'t, T> where T: Haystack + ?Sized {}
 
-impl<'t> ExactSizeIterator for SubCapturesPos<'t> {}
+impl<'t, T> ExactSizeIterator for SubCapturesPos<'t, T> where T: Haystack + ?Sized {}
 
 /// An iterator over all non-overlapping matches for a particular string.
 ///
@@ -353,15 +711,18 @@ impl<'t> ExactSizeIterator for SubCapturesPos<'t> {}
 ///
 /// `'r` is the lifetime of the `Regex` struct and `'t` is the lifetime
 /// of the matched string.
-pub struct FindMatches<'r, 't> {
+pub struct FindMatches<'r, 't, T: ?Sized = str> {
     regex: &'r Regex,
     region: Region,
-    text: &'t str,
+    text: &'t T,
     last_end: usize,
     last_match_end: Option<usize>,
 }
 
-impl<'r, 't> Iterator for FindMatches<'r, 't> {
+impl<'r, 't, T> Iterator for FindMatches<'r, 't, T>
+where
+    T: Haystack + ?Sized,
+{
     type Item = (usize, usize);
 
     fn next(&mut self) -> Option<(usize, usize)> {
@@ -369,8 +730,8 @@ impl<'r, 't> Iterator for FindMatches<'r, 't> {
             return None;
         }
         self.region.clear();
-        self.regex.search_with_options(
-            self.text,
+        self.regex.search_with_encoding(
+            self.text.encoded(self.regex),
             self.last_end,
             self.text.len(),
             SearchOptions::SEARCH_OPTION_NONE,
@@ -380,12 +741,8 @@ impl<'r, 't> Iterator for FindMatches<'r, 't> {
 
         // Don't accept empty matches immediately following the last match.
         // i.e., no infinite loops please.
-        if e == s && self.last_match_end.map_or(false, |l| l == e) {
-            self.last_end += self.text[self.last_end..]
-                .chars()
-                .next()
-                .map(|c| c.len_utf8())
-                .unwrap_or(1);
+        if e == s && self.last_match_end == Some(e) {
+            self.last_end += self.text.char_len(self.regex, self.last_end);
             return self.next();
         } else {
             self.last_end = e;
@@ -396,7 +753,71 @@ impl<'r, 't> Iterator for FindMatches<'r, 't> {
     }
 }
 
-impl<'r, 't> FusedIterator for FindMatches<'r, 't> {}
+impl<'r, 't, T> FusedIterator for FindMatches<'r, 't, T> where T: Haystack + ?Sized {}
+
+/// An iterator over all non-overlapping matches for a particular
+/// string, which reports engine errors.
+///
+/// The iterator yields a `Result` for each match. Successful matches
+/// hold the start and end byte offsets of the match. If the search
+/// fails the error is yielded once and the iterator then stops.
+///
+/// `'r` is the lifetime of the `Regex` struct and `'t` is the lifetime
+/// of the matched string.
+pub struct TryFindMatches<'r, 't, T: ?Sized = str> {
+    regex: &'r Regex,
+    match_param: &'r MatchParam,
+    region: Region,
+    text: &'t T,
+    last_end: usize,
+    last_match_end: Option<usize>,
+    failed: bool,
+}
+
+impl<'r, 't, T> Iterator for TryFindMatches<'r, 't, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = Result<(usize, usize), Error>;
+
+    fn next(&mut self) -> Option<Result<(usize, usize), Error>> {
+        if self.failed || self.last_end > self.text.len() {
+            return None;
+        }
+        self.region.clear();
+        let r = self.regex.search_raw(
+            self.text.encoded(self.regex),
+            self.last_end,
+            self.text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut self.region),
+            self.match_param,
+        );
+        match r {
+            Ok(Some(_)) => {}
+            Ok(None) => return None,
+            Err(e) => {
+                self.failed = true;
+                return Some(Err(e));
+            }
+        }
+        let (s, e) = self.region.pos(0).unwrap();
+
+        // Don't accept empty matches immediately following the last match.
+        // i.e., no infinite loops please.
+        if e == s && self.last_match_end == Some(e) {
+            self.last_end += self.text.char_len(self.regex, self.last_end);
+            return self.next();
+        } else {
+            self.last_end = e;
+            self.last_match_end = Some(e);
+        }
+
+        Some(Ok((s, e)))
+    }
+}
+
+impl<'r, 't, T> FusedIterator for TryFindMatches<'r, 't, T> where T: Haystack + ?Sized {}
 
 /// An iterator that yields all non-overlapping capture groups matching a
 /// particular regular expression.
@@ -405,24 +826,27 @@ impl<'r, 't> FusedIterator for FindMatches<'r, 't> {}
 ///
 /// `'r` is the lifetime of the `Regex` struct and `'t` is the lifetime
 /// of the matched string.
-pub struct FindCaptures<'r, 't> {
+pub struct FindCaptures<'r, 't, T: ?Sized = str> {
     regex: &'r Regex,
-    text: &'t str,
+    text: &'t T,
     last_end: usize,
     last_match_end: Option<usize>,
 }
 
-impl<'r, 't> Iterator for FindCaptures<'r, 't> {
-    type Item = Captures<'t>;
+impl<'r, 't, T> Iterator for FindCaptures<'r, 't, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = Captures<'t, T>;
 
-    fn next(&mut self) -> Option<Captures<'t>> {
+    fn next(&mut self) -> Option<Captures<'t, T>> {
         if self.last_end > self.text.len() {
             return None;
         }
 
         let mut region = Region::new();
-        let r = self.regex.search_with_options(
-            self.text,
+        let r = self.regex.search_with_encoding(
+            self.text.encoded(self.regex),
             self.last_end,
             self.text.len(),
             SearchOptions::SEARCH_OPTION_NONE,
@@ -432,53 +856,48 @@ impl<'r, 't> Iterator for FindCaptures<'r, 't> {
 
         // Don't accept empty matches immediately following the last match.
         // i.e., no infinite loops please.
-        if e == s && self.last_match_end.map_or(false, |l| l == e) {
-            self.last_end += self.text[self.last_end..]
-                .chars()
-                .next()
-                .map(|c| c.len_utf8())
-                .unwrap_or(1);
+        if e == s && self.last_match_end == Some(e) {
+            self.last_end += self.text.char_len(self.regex, self.last_end);
             return self.next();
         } else {
             self.last_end = e;
             self.last_match_end = Some(e);
         }
-        Some(Captures {
-            text: self.text,
-            region,
-            offset: r,
-        })
+        Some(Captures::new(self.regex, self.text, region, r))
     }
 }
 
-impl<'r, 't> FusedIterator for FindCaptures<'r, 't> {}
+impl<'r, 't, T> FusedIterator for FindCaptures<'r, 't, T> where T: Haystack + ?Sized {}
 
 /// Yields all substrings delimited by a regular expression match.
 ///
 /// `'r` is the lifetime of the compiled expression and `'t` is the lifetime
 /// of the string being split.
-pub struct RegexSplits<'r, 't> {
-    finder: FindMatches<'r, 't>,
+pub struct RegexSplits<'r, 't, T: ?Sized = str> {
+    finder: FindMatches<'r, 't, T>,
     last: usize,
 }
 
-impl<'r, 't> Iterator for RegexSplits<'r, 't> {
-    type Item = &'t str;
+impl<'r, 't, T> Iterator for RegexSplits<'r, 't, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = &'t T;
 
-    fn next(&mut self) -> Option<&'t str> {
+    fn next(&mut self) -> Option<&'t T> {
         let text = self.finder.text;
         match self.finder.next() {
             None => {
                 if self.last >= text.len() {
                     None
                 } else {
-                    let s = &text[self.last..];
+                    let s = text.slice(self.last, text.len());
                     self.last = text.len();
                     Some(s)
                 }
             }
             Some((s, e)) => {
-                let matched = &text[self.last..s];
+                let matched = text.slice(self.last, s);
                 self.last = e;
                 Some(matched)
             }
@@ -486,7 +905,7 @@ impl<'r, 't> Iterator for RegexSplits<'r, 't> {
     }
 }
 
-impl<'r, 't> FusedIterator for RegexSplits<'r, 't> {}
+impl<'r, 't, T> FusedIterator for RegexSplits<'r, 't, T> where T: Haystack + ?Sized {}
 
 /// Yields at most `N` substrings delimited by a regular expression match.
 ///
@@ -494,22 +913,25 @@ impl<'r, 't> FusedIterator for RegexSplits<'r, 't> {}
 ///
 /// `'r` is the lifetime of the compiled expression and `'t` is the lifetime
 /// of the string being split.
-pub struct RegexSplitsN<'r, 't> {
-    splits: RegexSplits<'r, 't>,
+pub struct RegexSplitsN<'r, 't, T: ?Sized = str> {
+    splits: RegexSplits<'r, 't, T>,
     n: usize,
 }
 
-impl<'r, 't> Iterator for RegexSplitsN<'r, 't> {
-    type Item = &'t str;
+impl<'r, 't, T> Iterator for RegexSplitsN<'r, 't, T>
+where
+    T: Haystack + ?Sized,
+{
+    type Item = &'t T;
 
-    fn next(&mut self) -> Option<&'t str> {
+    fn next(&mut self) -> Option<&'t T> {
         if self.n == 0 {
             return None;
         }
         self.n -= 1;
         if self.n == 0 {
             let text = self.splits.finder.text;
-            Some(&text[self.splits.last..])
+            Some(text.slice(self.splits.last, text.len()))
         } else {
             self.splits.next()
         }
@@ -520,7 +942,7 @@ impl<'r, 't> Iterator for RegexSplitsN<'r, 't> {
     }
 }
 
-impl<'r, 't> FusedIterator for RegexSplitsN<'r, 't> {}
+impl<'r, 't, T> FusedIterator for RegexSplitsN<'r, 't, T> where T: Haystack + ?Sized {}
 
 #[cfg(test)]
 mod tests {
@@ -566,6 +988,66 @@ mod tests {
         assert_eq!(caps.len(), 2);
     }
 
+    #[test]
+    fn test_regex_captures_name() {
+        let regex = Regex::new(r"(?<first>\w+)\s+(?<last>\w+)|(?<none>x)").unwrap();
+        let captures = regex.captures("John Smith").unwrap();
+        assert_eq!(captures.name("first"), Some("John"));
+        assert_eq!(captures.name_pos("last"), Some((5, 10)));
+        assert_eq!(captures.name("none"), None);
+        assert_eq!(captures.name("missing"), None);
+    }
+
+    #[test]
+    fn test_regex_captures_name_multiplex() {
+        let regex = Regex::new(r"(?<n>a)|(?<n>b)").unwrap();
+        assert_eq!(regex.captures("a").unwrap().name("n"), Some("a"));
+        assert_eq!(regex.captures("b").unwrap().name("n"), Some("b"));
+    }
+
+    #[test]
+    fn test_regex_captures_index() {
+        let regex = Regex::new(r"(?<key>\w+)=(?<value>\w+)").unwrap();
+        let captures = regex.captures("x = a=b").unwrap();
+        assert_eq!(&captures[0], "a=b");
+        assert_eq!(&captures[1], "a");
+        assert_eq!(&captures["value"], "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "no group named 'missing'")]
+    fn test_regex_captures_index_missing_name() {
+        let regex = Regex::new(r"(?<key>\w+)").unwrap();
+        let captures = regex.captures("a").unwrap();
+        let _ = &captures["missing"];
+    }
+
+    #[test]
+    fn test_regex_captures_iter_named() {
+        let regex = Regex::new(r"(?<a>x)(?<c>y)?(?<b>z)").unwrap();
+        let captures = regex.captures("xz").unwrap();
+        let named = captures.iter_named().collect::<Vec<_>>();
+        assert_eq!(
+            named,
+            vec![
+                (None, Some("xz")),
+                (Some("a"), Some("x")),
+                (Some("c"), None),
+                (Some("b"), Some("z")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_captures_outlive_regex() {
+        let text = String::from("k=v");
+        let captures = {
+            let regex = Regex::new(r"(?<key>\w)=").unwrap();
+            regex.captures(&text).unwrap()
+        };
+        assert_eq!(captures.name("key"), Some("k"));
+    }
+
     #[test]
     fn test_find_iter() {
         let re = Regex::new(r"\d+").unwrap();
@@ -601,6 +1083,47 @@ mod tests {
         assert_eq!(matches, [(0, 0), (4, 4), (5, 5), (11, 11)]);
     }
 
+    #[test]
+    fn test_try_captures() {
+        let re = Regex::new(r"e(l+)|(r+)").unwrap();
+        let param = MatchParam::default();
+        let caps = re.try_captures("hello", &param).unwrap().unwrap();
+        assert_eq!(caps.pos(0), Some((1, 4)));
+        assert_eq!(caps.at(1), Some("ll"));
+        assert!(re.try_captures("abc", &param).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_try_captures_error() {
+        let re = Regex::new("(a|b|ab)*bc").unwrap();
+        let mut param = MatchParam::default();
+        param.set_retry_limit_in_match(10);
+        let e = re.try_captures("abababababababacbc", &param).unwrap_err();
+        assert_eq!("retry-limit-in-match over", e.description());
+    }
+
+    #[test]
+    fn test_try_find_iter() {
+        let re = Regex::new(r"\d*").unwrap();
+        let param = MatchParam::default();
+        let ms = re
+            .try_find_iter("a1bbb2", &param)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(ms, vec![(0, 0), (1, 2), (3, 3), (4, 4), (5, 6)]);
+    }
+
+    #[test]
+    fn test_try_find_iter_stops_after_error() {
+        let re = Regex::new("(a|b|ab)*bc|x").unwrap();
+        let mut param = MatchParam::default();
+        param.set_retry_limit_in_match(10);
+        let mut iter = re.try_find_iter("x abababababababacbc x", &param);
+        assert_eq!(iter.next().unwrap().unwrap(), (0, 1));
+        assert!(iter.next().unwrap().is_err());
+        assert!(iter.next().is_none());
+    }
+
     #[test]
     fn test_captures_iter() {
         let re = Regex::new(r"\d+").unwrap();
@@ -36,6 +36,30 @@ bitflags! {
         /// Named and no-named group captured.
         const REGEX_OPTION_CAPTURE_GROUP
             = onig_sys::ONIG_OPTION_CAPTURE_GROUP;
+        /// Only ASCII characters match case insensitively when
+        /// `REGEX_OPTION_IGNORECASE` is set.
+        const REGEX_OPTION_IGNORECASE_IS_ASCII
+            = onig_sys::ONIG_OPTION_IGNORECASE_IS_ASCII;
+        /// `\w`, `\b` and `\p{Word}` only match ASCII.
+        const REGEX_OPTION_WORD_IS_ASCII
+            = onig_sys::ONIG_OPTION_WORD_IS_ASCII;
+        /// `\d`, `\p{Digit}` and `[[:digit:]]` only match ASCII.
+        const REGEX_OPTION_DIGIT_IS_ASCII
+            = onig_sys::ONIG_OPTION_DIGIT_IS_ASCII;
+        /// `\s`, `\p{Space}` and `[[:space:]]` only match ASCII.
+        const REGEX_OPTION_SPACE_IS_ASCII
+            = onig_sys::ONIG_OPTION_SPACE_IS_ASCII;
+        /// POSIX brackets, such as `[[:alpha:]]`, and the matching
+        /// properties only match ASCII.
+        const REGEX_OPTION_POSIX_IS_ASCII
+            = onig_sys::ONIG_OPTION_POSIX_IS_ASCII;
+        /// `\X` and `\y` use extended grapheme cluster text
+        /// segments.
+        const REGEX_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER
+            = onig_sys::ONIG_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER;
+        /// `\X` and `\y` use word text segments.
+        const REGEX_OPTION_TEXT_SEGMENT_WORD
+            = onig_sys::ONIG_OPTION_TEXT_SEGMENT_WORD;
     }
 }
 
@@ -54,6 +78,34 @@ bitflags! {
         /// Try and match the pattern against the whole string.
         const SEARCH_OPTION_WHOLE_STRING
             = onig_sys::ONIG_OPTION_MATCH_WHOLE_STRING;
+        /// Ignore empty matches.
+        const SEARCH_OPTION_FIND_NOT_EMPTY
+            = onig_sys::ONIG_OPTION_FIND_NOT_EMPTY;
+        /// String head isn't considered as the beginning of the string
+        /// (`\A`).
+        const SEARCH_OPTION_NOT_BEGIN_STRING
+            = onig_sys::ONIG_OPTION_NOT_BEGIN_STRING;
+        /// String end isn't considered as the end of the string (`\z`
+        /// and `\Z`).
+        const SEARCH_OPTION_NOT_END_STRING
+            = onig_sys::ONIG_OPTION_NOT_END_STRING;
+        /// The start of the search isn't considered as the search
+        /// position (`\G`).
+        const SEARCH_OPTION_NOT_BEGIN_POSITION
+            = onig_sys::ONIG_OPTION_NOT_BEGIN_POSITION;
+        /// Call the closure set with
+        /// `MatchParam::set_each_match_callback` for every match,
+        /// rather than stopping at the first one. The search itself
+        /// then doesn't match.
+        const SEARCH_OPTION_CALLBACK_EACH_MATCH
+            = onig_sys::ONIG_OPTION_CALLBACK_EACH_MATCH;
+        /// Check the string is valid in the regex's encoding before
+        /// searching it, failing with an `ErrorKind::InvalidEncoding`
+        /// error if it isn't. Use this when searching untrusted bytes.
+        /// Only the `*_with_param` and `try_*` methods report the
+        /// error; the other search methods panic on it.
+        const SEARCH_OPTION_CHECK_VALIDITY_OF_STRING
+            = onig_sys::ONIG_OPTION_CHECK_VALIDITY_OF_STRING;
     }
 }
 
@@ -207,6 +259,12 @@ bitflags! {
         /// `\`
         const SYNTAX_OPERATOR_INEFFECTIVE_ESCAPE
             = (onig_sys::ONIG_SYN_OP2_INEFFECTIVE_ESCAPE as u64) << 32;
+        /// `(?{...})`
+        const SYNTAX_OPERATOR_QMARK_BRACE_CALLOUT_CONTENTS
+            = (onig_sys::ONIG_SYN_OP2_QMARK_BRACE_CALLOUT_CONTENTS as u64) << 32;
+        /// `(*name)`, `(*name[tag]{args...})`
+        const SYNTAX_OPERATOR_ASTERISK_CALLOUT_NAME
+            = (onig_sys::ONIG_SYN_OP2_ASTERISK_CALLOUT_NAME as u64) << 32;
     }
 }
 
@@ -261,6 +319,27 @@ bitflags! {
     }
 }
 
+bitflags! {
+    /// Case folding flags, used when matching case insensitively.
+    pub struct CaseFold: onig_sys::OnigCaseFoldType {
+        /// Only fold ASCII characters.
+        const CASE_FOLD_ASCII_ONLY
+            = onig_sys::ONIGENC_CASE_FOLD_ASCII_ONLY;
+        /// Fold characters which expand to more than one character,
+        /// such as `ß` and `ss`.
+        const CASE_FOLD_MULTI_CHAR
+            = onig_sys::INTERNAL_ONIGENC_CASE_FOLD_MULTI_CHAR;
+        /// Use the Turkish and Azerbaijani folding of the dotted and
+        /// dotless `i`. Oniguruma only supports this when it is built
+        /// with `USE_UNICODE_CASE_FOLD_TURKISH_AZERI` defined. The
+        /// bundled copy is, but a system library linked with
+        /// `RUSTONIG_SYSTEM_LIBONIG` usually isn't, in which case
+        /// this flag has no effect.
+        const CASE_FOLD_TURKISH_AZERI
+            = onig_sys::ONIGENC_CASE_FOLD_TURKISH_AZERI;
+    }
+}
+
 bitflags! {
     /// The order in which traverse callbacks are invoked
     pub struct TraverseCallbackAt: c_uint {
@@ -293,3 +372,167 @@ bitflags! {
         const META_CHAR_ANYCHAR_ANYTIME =  onig_sys::ONIG_META_CHAR_ANYCHAR_ANYTIME;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn find_with(pattern: &str, options: RegexOptions, text: &str) -> Option<(usize, usize)> {
+        Regex::with_options(pattern, options, Syntax::default())
+            .unwrap()
+            .find(text)
+    }
+
+    fn search_with(
+        pattern: &str,
+        text: &str,
+        from: usize,
+        options: SearchOptions,
+    ) -> Option<usize> {
+        Regex::new(pattern)
+            .unwrap()
+            .search_with_options(text, from, text.len(), options, None)
+    }
+
+    #[test]
+    fn test_ignorecase_is_ascii() {
+        let ignorecase = RegexOptions::REGEX_OPTION_IGNORECASE;
+        let ascii = ignorecase | RegexOptions::REGEX_OPTION_IGNORECASE_IS_ASCII;
+        assert!(find_with("é", ignorecase, "É").is_some());
+        assert!(find_with("é", ascii, "É").is_none());
+        assert!(find_with("e", ascii, "E").is_some());
+    }
+
+    #[test]
+    fn test_class_is_ascii() {
+        let none = RegexOptions::REGEX_OPTION_NONE;
+        let cases = [
+            (r"\w", "é", RegexOptions::REGEX_OPTION_WORD_IS_ASCII),
+            (r"\d", "٣", RegexOptions::REGEX_OPTION_DIGIT_IS_ASCII),
+            (r"\s", "\u{3000}", RegexOptions::REGEX_OPTION_SPACE_IS_ASCII),
+            (
+                "[[:alpha:]]",
+                "é",
+                RegexOptions::REGEX_OPTION_POSIX_IS_ASCII,
+            ),
+        ];
+        for &(pattern, text, option) in &cases {
+            assert!(find_with(pattern, none, text).is_some(), "{}", pattern);
+            assert!(find_with(pattern, option, text).is_none(), "{}", pattern);
+        }
+    }
+
+    #[test]
+    fn test_text_segment() {
+        let text = "he\u{301}llo world";
+        assert_eq!(
+            find_with(
+                r"\X\X",
+                RegexOptions::REGEX_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER,
+                text
+            ),
+            Some((0, 4))
+        );
+        assert_eq!(
+            find_with(r"\X", RegexOptions::REGEX_OPTION_TEXT_SEGMENT_WORD, text),
+            Some((0, 7))
+        );
+    }
+
+    #[test]
+    fn test_case_fold() {
+        let regex = |pattern, case_fold| {
+            RegexBuilder::new(pattern)
+                .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+                .case_fold_flag(case_fold)
+                .build()
+                .unwrap()
+        };
+
+        let turkish = CaseFold::CASE_FOLD_MULTI_CHAR | CaseFold::CASE_FOLD_TURKISH_AZERI;
+        assert_eq!(regex("i", turkish).case_fold_flag(), turkish);
+
+        assert!(regex("é", CaseFold::CASE_FOLD_MULTI_CHAR).is_match("É"));
+        assert!(!regex("é", CaseFold::CASE_FOLD_ASCII_ONLY).is_match("É"));
+        assert!(regex("e", CaseFold::CASE_FOLD_ASCII_ONLY).is_match("E"));
+
+        assert!(regex("ss", CaseFold::CASE_FOLD_MULTI_CHAR).is_match("ß"));
+        assert!(!regex("ss", CaseFold::empty()).is_match("ß"));
+    }
+
+    #[test]
+    #[cfg_attr(
+        not(onig_bundled),
+        ignore = "needs an Oniguruma built with USE_UNICODE_CASE_FOLD_TURKISH_AZERI"
+    )]
+    fn test_case_fold_turkish_azeri() {
+        let regex = |pattern, case_fold| {
+            RegexBuilder::new(pattern)
+                .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+                .case_fold_flag(case_fold)
+                .build()
+                .unwrap()
+        };
+
+        let turkish = CaseFold::CASE_FOLD_MULTI_CHAR | CaseFold::CASE_FOLD_TURKISH_AZERI;
+        assert!(regex("i", CaseFold::CASE_FOLD_MULTI_CHAR).is_match("I"));
+        assert!(regex("i", turkish).is_match("İ"));
+        assert!(!regex("i", turkish).is_match("I"));
+        assert!(regex("ı", turkish).is_match("I"));
+    }
+
+    #[test]
+    fn test_search_find_not_empty() {
+        assert_eq!(
+            search_with("a*", "baa", 0, SearchOptions::SEARCH_OPTION_NONE),
+            Some(0)
+        );
+        assert_eq!(
+            search_with("a*", "baa", 0, SearchOptions::SEARCH_OPTION_FIND_NOT_EMPTY),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_search_not_begin_or_end() {
+        let none = SearchOptions::SEARCH_OPTION_NONE;
+        assert_eq!(search_with(r"\Aa", "ab", 0, none), Some(0));
+        assert_eq!(
+            search_with(
+                r"\Aa",
+                "ab",
+                0,
+                SearchOptions::SEARCH_OPTION_NOT_BEGIN_STRING
+            ),
+            None
+        );
+        assert_eq!(search_with(r"b\z", "ab", 0, none), Some(1));
+        assert_eq!(
+            search_with(r"b\z", "ab", 0, SearchOptions::SEARCH_OPTION_NOT_END_STRING),
+            None
+        );
+        assert_eq!(search_with(r"\Gb", "ab", 1, none), Some(1));
+        assert_eq!(
+            search_with(
+                r"\Gb",
+                "ab",
+                1,
+                SearchOptions::SEARCH_OPTION_NOT_BEGIN_POSITION
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_search_check_validity_of_string() {
+        let regex = bytes::Regex::with_encoding(EncodedBytes::new(b"a", Encoding::Utf8)).unwrap();
+        let text = b"\x80a";
+        let search = |options| {
+            regex.search_with_param(text, 0, text.len(), options, None, MatchParam::default())
+        };
+        assert_eq!(search(SearchOptions::SEARCH_OPTION_NONE).unwrap(), Some(1));
+        let err = search(SearchOptions::SEARCH_OPTION_CHECK_VALIDITY_OF_STRING).unwrap_err();
+        assert!(err.is_invalid_encoding());
+        assert_eq!(err.code(), onig_sys::ONIGERR_INVALID_WIDE_CHAR_VALUE);
+    }
+}
@@ -69,6 +69,25 @@
 //! and end of the match as `Regex::find` does, the latter exposes the
 //! whole capture group information as `Regex::captures` does.
 //!
+//! ## Handling Errors
+//!
+//! The search and match APIs above return `None` when the pattern
+//! doesn't match, and panic if the engine itself fails, for example
+//! when a retry limit is exceeded. The `try_` family of methods,
+//! `Regex::try_search`, `Regex::try_match`, `Regex::try_captures` and
+//! `Regex::try_find_iter`, instead return a `Result` so that these
+//! failures can be handled. Each takes a `MatchParam` which can be
+//! used to set the limits for the search.
+//!
+//! # Searching Bytes
+//!
+//! The `bytes` module contains a `bytes::Regex` type which searches
+//! `&[u8]` buffers rather than `&str`. It provides the same *search*,
+//! *find* and replace APIs, with captures yielding byte slices.
+//! Similarly the `utf16` module contains a `utf16::Regex` type which
+//! searches native-endian UTF-16 `&[u16]` buffers, with positions
+//! measured in code units.
+//!
 //! # The `std::pattern` API
 //!
 //! In addition to the main Oniguruma API it is possible to use the
@@ -83,6 +102,43 @@
 //! version = "1.2"
 //! features = ["std-pattern"]
 //! ```
+//!
+//! # Checking Patterns at Compile Time
+//!
+//! With the `macros` feature enabled the `regex!` macro compiles a
+//! literal pattern when your crate is built, so mistakes in the
+//! pattern are reported as compile errors. It expands to a lazily
+//! initialised `&'static Regex`:
+//!
+//! ```ignore
+//! let date = onig::regex!(r"(\d{4})-(\d{2})-(\d{2})");
+//! assert!(date.is_match("2015-06-28"));
+//! ```
+//!
+//! # Pattern Warnings
+//!
+//! Warnings Oniguruma gives while compiling a pattern can be collected
+//! with `set_warning_handler`. Alternatively enable the `log` feature
+//! to have them logged through the [`log`](https://docs.rs/log) crate.
+//!
+//! # Serialization
+//!
+//! With the `serde` feature enabled `Regex` implements `Serialize` and
+//! `Deserialize`. A regex is stored as its pattern, options, syntax
+//! name and encoding name, and is recompiled when it is deserialized.
+//! Only the pattern is required, so regexes can be loaded from
+//! configuration files:
+//!
+//! ```ignore
+//! let regex: onig::Regex = serde_json::from_str(r#"{
+//!     "pattern": "(?<key>\\w+)=(?<value>\\w+)",
+//!     "options": ["REGEX_OPTION_IGNORECASE"],
+//!     "syntax": "perl_ng"
+//! }"#)?;
+//! ```
+//!
+//! The option flag types, such as `RegexOptions` and `SyntaxOperator`,
+//! are stored as lists of flag names.
 
 #![cfg_attr(not(feature = "cargo-clippy"), allow(unknown_lints))]
 #![cfg_attr(feature = "std-pattern", feature(pattern))]
@@ -91,73 +147,273 @@
 use once_cell::sync::Lazy;
 
 mod buffers;
+mod builder;
+pub mod bytes;
+mod callout;
+pub mod defaults;
+mod encoding;
 mod find;
 mod flags;
+pub mod limits;
 mod match_param;
 mod names;
 mod region;
+mod regset;
 mod replace;
+pub mod segment;
+#[cfg(feature = "serde")]
+mod serialization;
 mod syntax;
 mod tree;
+pub mod utf16;
 mod utils;
+mod warnings;
 
 #[cfg(feature = "std-pattern")]
 mod pattern;
 
 // re-export the onig types publically
 pub use crate::buffers::{EncodedBytes, EncodedChars};
+pub use crate::builder::RegexBuilder;
+pub use crate::callout::{
+    register_callout, register_callout_with_encoding, CalloutArgs, CalloutCounter, CalloutResult,
+};
+pub use crate::encoding::Encoding;
 pub use crate::find::{
-    Captures, FindCaptures, FindMatches, RegexSplits, RegexSplitsN, SubCaptures, SubCapturesPos,
+    Captures, FindCaptures, FindMatches, RegexSplits, RegexSplitsN, SubCaptures, SubCapturesNamed,
+    SubCapturesPos, TryFindMatches,
 };
 pub use crate::flags::*;
 pub use crate::match_param::MatchParam;
+pub use crate::names::CaptureNames;
 pub use crate::region::Region;
+pub use crate::regset::{RegSet, RegSetLead};
 pub use crate::replace::Replacer;
 pub use crate::syntax::{MetaChar, Syntax};
 pub use crate::tree::{CaptureTreeNode, CaptureTreeNodeIter};
+pub use crate::utf16::EncodedUtf16;
 pub use crate::utils::{copyright, define_user_property, version};
+pub use crate::warnings::{clear_warning_handler, set_warning_handler};
+
+#[cfg(feature = "macros")]
+pub use onig_macros::regex;
+
+/// Items used by the expansion of `regex!`. Not public API.
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod __private {
+    pub use once_cell::sync::Lazy;
+}
 
 use std::os::raw::c_int;
-use std::ptr::{null, null_mut};
-use std::sync::Mutex;
+use std::ptr::null;
+use std::sync::{Arc, Mutex};
 use std::{error, fmt, str};
 
 #[derive(Debug)]
 enum ErrorData {
     OnigError(c_int),
-    Custom,
+    Custom(ErrorKind),
+}
+
+/// The category of an `Error`.
+///
+/// Groups the many error codes returned by Oniguruma into families
+/// which can be handled without matching on the raw `code()`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The pattern is not valid in the chosen syntax.
+    Syntax,
+    /// One of the engine's limits was exceeded. For example the
+    /// retry limit or match stack limit when searching, or the parse
+    /// depth when compiling.
+    LimitExceeded,
+    /// The pattern or haystack encoding is invalid, or a mix of
+    /// encodings was used.
+    InvalidEncoding,
+    /// The engine failed to allocate memory.
+    Memory,
+    /// The pattern or options use a feature which isn't supported,
+    /// or which Oniguruma was built without.
+    Unsupported,
+    /// The pattern refers to a name which isn't defined, such as a
+    /// group name, character property or callout.
+    UndefinedName,
+    /// An invalid argument was passed to the engine.
+    InvalidArgument,
+    /// The search was aborted, either by a callout or because the
+    /// deadline or cancellation token of its `MatchParam` fired.
+    Aborted,
+    /// The engine hit an internal error, or returned an error code
+    /// this crate doesn't know about.
+    Internal,
+}
+
+impl ErrorKind {
+    fn from_code(code: c_int) -> ErrorKind {
+        use onig_sys::*;
+
+        match code {
+            ONIGERR_MEMORY => ErrorKind::Memory,
+            ONIGERR_MATCH_STACK_LIMIT_OVER
+            | ONIGERR_PARSE_DEPTH_LIMIT_OVER
+            | ONIGERR_RETRY_LIMIT_IN_MATCH_OVER
+            | ONIGERR_RETRY_LIMIT_IN_SEARCH_OVER
+            | ONIGERR_SUBEXP_CALL_LIMIT_IN_SEARCH_OVER
+            | ONIGERR_TOO_MANY_MULTI_BYTE_RANGES
+            | ONIGERR_TOO_MANY_CAPTURES
+            | ONIGERR_GROUP_NUMBER_OVER_FOR_CAPTURE_HISTORY
+            | ONIGERR_TOO_MANY_USER_DEFINED_OBJECTS => ErrorKind::LimitExceeded,
+            ONIGERR_DEFAULT_ENCODING_IS_NOT_SET
+            | ONIGERR_SPECIFIED_ENCODING_CANT_CONVERT_TO_WIDE_CHAR
+            | ONIGERR_TOO_SHORT_MULTI_BYTE_STRING
+            | ONIGERR_INVALID_CODE_POINT_VALUE
+            | ONIGERR_TOO_BIG_WIDE_CHAR_VALUE
+            | ONIGERR_TOO_LONG_WIDE_CHAR_VALUE
+            | ONIGERR_NOT_SUPPORTED_ENCODING_COMBINATION => ErrorKind::InvalidEncoding,
+            ONIG_NO_SUPPORT_CONFIG
+            | ONIGERR_NUMBERED_BACKREF_OR_CALL_NOT_ALLOWED
+            | ONIGERR_UNDEFINED_OPERATOR
+            | ONIGERR_MULTIPLEX_DEFINITION_NAME_CALL
+            | ONIGERR_INVALID_COMBINATION_OF_OPTIONS
+            | ONIGERR_VERY_INEFFICIENT_PATTERN => ErrorKind::Unsupported,
+            ONIGERR_UNDEFINED_NAME_REFERENCE
+            | ONIGERR_UNDEFINED_GROUP_REFERENCE
+            | ONIGERR_INVALID_CHAR_PROPERTY_NAME
+            | ONIGERR_TOO_LONG_PROPERTY_NAME
+            | ONIGERR_UNDEFINED_CALLOUT_NAME => ErrorKind::UndefinedName,
+            ONIGERR_INVALID_ARGUMENT => ErrorKind::InvalidArgument,
+            ONIG_ABORT => ErrorKind::Aborted,
+            ONIGERR_END_PATTERN_AT_LEFT_BRACE
+            | ONIGERR_END_PATTERN_AT_LEFT_BRACKET
+            | ONIGERR_EMPTY_CHAR_CLASS
+            | ONIGERR_PREMATURE_END_OF_CHAR_CLASS
+            | ONIGERR_END_PATTERN_AT_ESCAPE
+            | ONIGERR_END_PATTERN_AT_META
+            | ONIGERR_END_PATTERN_AT_CONTROL
+            | ONIGERR_META_CODE_SYNTAX
+            | ONIGERR_CONTROL_CODE_SYNTAX
+            | ONIGERR_CHAR_CLASS_VALUE_AT_END_OF_RANGE
+            | ONIGERR_CHAR_CLASS_VALUE_AT_START_OF_RANGE
+            | ONIGERR_UNMATCHED_RANGE_SPECIFIER_IN_CHAR_CLASS
+            | ONIGERR_TARGET_OF_REPEAT_OPERATOR_NOT_SPECIFIED
+            | ONIGERR_TARGET_OF_REPEAT_OPERATOR_INVALID
+            | ONIGERR_NESTED_REPEAT_OPERATOR
+            | ONIGERR_UNMATCHED_CLOSE_PARENTHESIS
+            | ONIGERR_END_PATTERN_WITH_UNMATCHED_PARENTHESIS
+            | ONIGERR_END_PATTERN_IN_GROUP
+            | ONIGERR_UNDEFINED_GROUP_OPTION
+            | ONIGERR_INVALID_GROUP_OPTION
+            | ONIGERR_INVALID_POSIX_BRACKET_TYPE
+            | ONIGERR_INVALID_LOOK_BEHIND_PATTERN
+            | ONIGERR_INVALID_REPEAT_RANGE_PATTERN
+            | ONIGERR_TOO_BIG_NUMBER
+            | ONIGERR_TOO_BIG_NUMBER_FOR_REPEAT_RANGE
+            | ONIGERR_UPPER_SMALLER_THAN_LOWER_IN_REPEAT_RANGE
+            | ONIGERR_EMPTY_RANGE_IN_CHAR_CLASS
+            | ONIGERR_MISMATCH_CODE_LENGTH_IN_CLASS_RANGE
+            | ONIGERR_TOO_BIG_BACKREF_NUMBER
+            | ONIGERR_INVALID_BACKREF
+            | ONIGERR_EMPTY_GROUP_NAME
+            | ONIGERR_INVALID_GROUP_NAME
+            | ONIGERR_INVALID_CHAR_IN_GROUP_NAME
+            | ONIGERR_MULTIPLEX_DEFINED_NAME
+            | ONIGERR_NEVER_ENDING_RECURSION
+            | ONIGERR_INVALID_IF_ELSE_SYNTAX
+            | ONIGERR_INVALID_ABSENT_GROUP_PATTERN
+            | ONIGERR_INVALID_ABSENT_GROUP_GENERATOR_PATTERN
+            | ONIGERR_INVALID_CALLOUT_PATTERN
+            | ONIGERR_INVALID_CALLOUT_NAME
+            | ONIGERR_INVALID_CALLOUT_BODY
+            | ONIGERR_INVALID_CALLOUT_TAG_NAME
+            | ONIGERR_INVALID_CALLOUT_ARG => ErrorKind::Syntax,
+            _ => ErrorKind::Internal,
+        }
+    }
 }
 
 /// This struture represents an error from the underlying Oniguruma libray.
 pub struct Error {
     data: ErrorData,
     description: String,
+    pattern: Option<String>,
+    span: Option<(usize, usize)>,
 }
 
 /// This struct is a wrapper around an Oniguruma regular expression
 /// pointer. This represents a compiled regex which can be used in
 /// search and match operations.
-#[derive(Debug, Eq, PartialEq)]
+///
+/// Cloning a `Regex` is cheap. Clones share the same compiled regex,
+/// which is freed once the last of them is dropped.
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct Regex {
+    raw: Arc<RawRegex>,
+}
+
+/// Owned Regex Pointer
+///
+/// Frees the compiled regex once the last reference is dropped. This
+/// is shared with any `Captures` so they can look up named groups.
+///
+/// Oniguruma keeps pointers to the pattern and syntax a regex was
+/// compiled with, so copies of both are owned alongside it.
+#[derive(Debug)]
+pub(crate) struct RawRegex {
     raw: onig_sys::OnigRegex,
+    pattern: Box<[u8]>,
+    // Only read through `onig_get_syntax`.
+    #[allow(dead_code)]
+    syntax: Box<Syntax>,
+}
+
+impl PartialEq for RawRegex {
+    fn eq(&self, other: &RawRegex) -> bool {
+        self.raw == other.raw
+    }
 }
 
-unsafe impl Send for Regex {}
-unsafe impl Sync for Regex {}
+impl Eq for RawRegex {}
+
+unsafe impl Send for RawRegex {}
+unsafe impl Sync for RawRegex {}
 
 impl Error {
-    fn from_code_and_info(code: c_int, info: &onig_sys::OnigErrorInfo) -> Self {
-        Error::new(code, info)
+    fn from_code_and_info<T>(code: c_int, info: &onig_sys::OnigErrorInfo, pattern: &T) -> Self
+    where
+        T: EncodedChars,
+    {
+        let mut error = Error::new(code, info);
+        let start = pattern.start_ptr() as usize;
+        let (par, par_end) = (info.par as usize, info.par_end as usize);
+        if !info.par.is_null() && start <= par && par <= par_end && par_end - start <= pattern.len()
+        {
+            error.span = Some((par - start, par_end - start));
+            // Keep hold of the pattern text so that `Display` can
+            // point at the span. Only UTF-8 compatible patterns can be
+            // shown this way.
+            if pattern.encoding() == Encoding::Utf8.as_raw()
+                || pattern.encoding() == Encoding::Ascii.as_raw()
+            {
+                let bytes =
+                    unsafe { std::slice::from_raw_parts(pattern.start_ptr(), pattern.len()) };
+                error.pattern = str::from_utf8(bytes).ok().map(str::to_owned);
+            }
+        }
+        error
     }
 
     fn from_code(code: c_int) -> Self {
         Error::new(code, null())
     }
 
-    fn custom<T: Into<String>>(message: T) -> Self {
+    fn custom<T: Into<String>>(kind: ErrorKind, message: T) -> Self {
         Error {
-            data: ErrorData::Custom,
+            data: ErrorData::Custom(kind),
             description: message.into(),
+            pattern: None,
+            span: None,
         }
     }
 
@@ -167,11 +423,13 @@ impl Error {
         let description = if let Ok(description) = str::from_utf8(&buff[..len as usize]) {
             description
         } else {
-            return Self::custom("Onig error string was invalid UTF-8");
+            return Self::custom(ErrorKind::Internal, "Onig error string was invalid UTF-8");
         };
         Error {
             data: ErrorData::OnigError(code),
             description: description.to_owned(),
+            pattern: None,
+            span: None,
         }
     }
 
@@ -183,10 +441,73 @@ impl Error {
         }
     }
 
+    /// Return the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        match self.data {
+            ErrorData::OnigError(code) => ErrorKind::from_code(code),
+            ErrorData::Custom(kind) => kind,
+        }
+    }
+
+    /// Is this a syntax error in the pattern?
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    /// Was one of the engine's limits exceeded?
+    pub fn is_limit_exceeded(&self) -> bool {
+        self.kind() == ErrorKind::LimitExceeded
+    }
+
+    /// Is this an encoding error?
+    pub fn is_invalid_encoding(&self) -> bool {
+        self.kind() == ErrorKind::InvalidEncoding
+    }
+
+    /// Did the engine run out of memory?
+    pub fn is_memory(&self) -> bool {
+        self.kind() == ErrorKind::Memory
+    }
+
+    /// Was an unsupported feature used?
+    pub fn is_unsupported(&self) -> bool {
+        self.kind() == ErrorKind::Unsupported
+    }
+
+    /// Does the pattern refer to an undefined name?
+    pub fn is_undefined_name(&self) -> bool {
+        self.kind() == ErrorKind::UndefinedName
+    }
+
+    /// Was the search aborted?
+    pub fn is_aborted(&self) -> bool {
+        self.kind() == ErrorKind::Aborted
+    }
+
     /// Return error description provided by Oniguruma engine.
     pub fn description(&self) -> &str {
         &self.description
     }
+
+    /// Return the Span of the Error Within the Pattern
+    ///
+    /// For errors raised when compiling a regex Oniguruma can report
+    /// the part of the pattern which caused the error, such as the
+    /// name of an undefined group. If it did this returns the start
+    /// and end byte offsets of that part relative to the start of the
+    /// pattern.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::Regex;
+    ///
+    /// let e = Regex::new(r"(?<foo>a)\k<bar>").unwrap_err();
+    /// assert_eq!(e.pattern_span(), Some((12, 15)));
+    /// ```
+    pub fn pattern_span(&self) -> Option<(usize, usize)> {
+        self.span
+    }
 }
 
 impl error::Error for Error {
@@ -197,10 +518,39 @@ impl error::Error for Error {
 
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Oniguruma error: {}", self.description())
+        write!(f, "Oniguruma error: {}", self.description())?;
+
+        // If we know where in the pattern the error is then draw a
+        // caret diagram under the line of the pattern containing it.
+        // Spans which don't fall on character boundaries are skipped.
+        if let (Some(pattern), Some((beg, end))) = (&self.pattern, self.span) {
+            if let Some((line, indent, width)) = caret_line(pattern, beg, end) {
+                write!(
+                    f,
+                    "\n    {}\n    {}{}",
+                    line,
+                    " ".repeat(indent),
+                    "^".repeat(width)
+                )?;
+            }
+        }
+
+        Ok(())
     }
 }
 
+/// Find the line of `pattern` containing `beg..end`, along with the
+/// indent and width of the caret to draw under it.
+fn caret_line(pattern: &str, beg: usize, end: usize) -> Option<(&str, usize, usize)> {
+    let before = pattern.get(..beg)?;
+    let after = pattern.get(beg..)?;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let line_end = after.find('\n').map_or(pattern.len(), |i| beg + i);
+    let indent = pattern.get(line_start..beg)?.chars().count();
+    let width = pattern.get(beg..end.min(line_end))?.chars().count().max(1);
+    Some((pattern.get(line_start..line_end)?, indent, width))
+}
+
 impl fmt::Debug for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Error({:?}, {})", self.data, self.description())
@@ -255,11 +605,7 @@ impl Regex {
     where
         T: EncodedChars,
     {
-        Regex::with_options_and_encoding(
-            pattern,
-            RegexOptions::REGEX_OPTION_NONE,
-            Syntax::default(),
-        )
+        RegexBuilder::with_encoding(pattern).build()
     }
 
     /// Create a new Regex
@@ -267,7 +613,7 @@ impl Regex {
     /// Attempts to compile a pattern into a new `Regex` instance.
     /// Once compiled, it can be used repeatedly to search in a string. If an
     /// invalid expression is given, then an error is returned.
-    /// See [`onig_sys::onig_new`][regex_new] for more information.
+    /// See [`onig_sys::onig_new_deluxe`][regex_new] for more information.
     ///
     /// # Arguments
     ///
@@ -285,7 +631,7 @@ impl Regex {
     /// assert!(r.is_ok());
     /// ```
     ///
-    /// [regex_new]: ./onig_sys/fn.onig_new.html
+    /// [regex_new]: ./onig_sys/fn.onig_new_deluxe.html
     pub fn with_options(
         pattern: &str,
         option: RegexOptions,
@@ -300,9 +646,9 @@ impl Regex {
     /// instance. Instead of assuming UTF-8 as the encoding scheme the
     /// encoding is inferred from the `pattern` buffer. If the regex
     /// fails to compile the returned `Error` value from
-    /// [`onig_new`][regex_new] contains more information.
+    /// [`onig_new_deluxe`][regex_new] contains more information.
     ///
-    /// [regex_new]: ./onig_sys/fn.onig_new.html
+    /// [regex_new]: ./onig_sys/fn.onig_new_deluxe.html
     ///
     /// # Arguments
     ///
@@ -327,39 +673,10 @@ impl Regex {
     where
         T: EncodedChars,
     {
-        // Convert the rust types to those required for the call to
-        // `onig_new`.
-        let mut reg: onig_sys::OnigRegex = null_mut();
-        let reg_ptr = &mut reg as *mut onig_sys::OnigRegex;
-
-        // We can use this later to get an error message to pass back
-        // if regex creation fails.
-        let mut error = onig_sys::OnigErrorInfo {
-            enc: null_mut(),
-            par: null_mut(),
-            par_end: null_mut(),
-        };
-
-        let err = unsafe {
-            // Grab a lock to make sure that `onig_new` isn't called by
-            // more than one thread at a time.
-            let _guard = REGEX_NEW_MUTEX.lock().unwrap();
-            onig_sys::onig_new(
-                reg_ptr,
-                pattern.start_ptr(),
-                pattern.limit_ptr(),
-                option.bits(),
-                pattern.encoding(),
-                syntax as *const Syntax as *mut Syntax as *mut onig_sys::OnigSyntaxType,
-                &mut error,
-            )
-        };
-
-        if err == onig_sys::ONIG_NORMAL as i32 {
-            Ok(Regex { raw: reg })
-        } else {
-            Err(Error::from_code_and_info(err, &error))
-        }
+        RegexBuilder::with_encoding(pattern)
+            .options(option)
+            .syntax(syntax)
+            .build()
     }
 
     /// Match String
@@ -507,40 +824,73 @@ impl Regex {
         region: Option<&mut Region>,
         match_param: MatchParam,
     ) -> Result<Option<usize>, Error>
+    where
+        T: EncodedChars,
+    {
+        self.match_raw(chars, at, options, region, &match_param)
+    }
+
+    fn match_raw<T>(
+        &self,
+        chars: T,
+        at: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+        match_param: &MatchParam,
+    ) -> Result<Option<usize>, Error>
     where
         T: EncodedChars,
     {
         if chars.encoding() != self.encoding() {
-            return Err(Error::custom(format!(
-                "Regex encoding does not match haystack encoding ({0:?}, {1:?})",
-                chars.encoding(),
-                self.encoding()
-            )));
+            return Err(Error::custom(
+                ErrorKind::InvalidEncoding,
+                format!(
+                    "Regex encoding does not match haystack encoding ({0:?}, {1:?})",
+                    chars.encoding(),
+                    self.encoding()
+                ),
+            ));
+        }
+        match_param.check_aborted()?;
+        let offset = unsafe { chars.start_ptr().add(at) };
+        if offset > chars.limit_ptr() {
+            return Err(Error::custom(
+                ErrorKind::InvalidArgument,
+                format!("Offset {} is too large", at),
+            ));
         }
-        let r = unsafe {
-            let offset = chars.start_ptr().add(at);
-            if offset > chars.limit_ptr() {
-                return Err(Error::custom(format!("Offset {} is too large", at)));
+        // The each match callback is given the region, so it needs one
+        // even if the caller doesn't.
+        let mut each_match_region = Region::new();
+        let region = match region {
+            Some(region) => region as *mut Region as *mut onig_sys::OnigRegion,
+            None if options.contains(SearchOptions::SEARCH_OPTION_CALLBACK_EACH_MATCH) => {
+                &mut each_match_region as *mut Region as *mut onig_sys::OnigRegion
             }
+            None => std::ptr::null_mut(),
+        };
+        let r = match_param.search_in_slices(|| unsafe {
             onig_sys::onig_match_with_param(
-                self.raw,
+                self.as_raw(),
                 chars.start_ptr(),
                 chars.limit_ptr(),
                 offset,
-                match region {
-                    Some(region) => region as *mut Region as *mut onig_sys::OnigRegion,
-                    None => std::ptr::null_mut(),
-                },
+                region,
                 options.bits(),
                 match_param.as_raw(),
             )
-        };
+        });
+        callout::resume_callout_panic();
 
         if r >= 0 {
             Ok(Some(r as usize))
         } else if r == onig_sys::ONIG_MISMATCH {
             Ok(None)
         } else {
+            // Report why a deadline or cancellation aborted the search.
+            if r == onig_sys::ONIG_ABORT {
+                match_param.check_aborted()?;
+            }
             Err(Error::from_code(r))
         }
     }
@@ -700,46 +1050,82 @@ impl Regex {
         region: Option<&mut Region>,
         match_param: MatchParam,
     ) -> Result<Option<usize>, Error>
+    where
+        T: EncodedChars,
+    {
+        self.search_raw(chars, from, to, options, region, &match_param)
+    }
+
+    pub(crate) fn search_raw<T>(
+        &self,
+        chars: T,
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+        match_param: &MatchParam,
+    ) -> Result<Option<usize>, Error>
     where
         T: EncodedChars,
     {
         let (beg, end) = (chars.start_ptr(), chars.limit_ptr());
         if chars.encoding() != self.encoding() {
-            return Err(Error::custom(format!(
-                "Regex encoding does not match haystack encoding ({0:?}, {1:?})",
-                chars.encoding(),
-                self.encoding()
-            )));
+            return Err(Error::custom(
+                ErrorKind::InvalidEncoding,
+                format!(
+                    "Regex encoding does not match haystack encoding ({0:?}, {1:?})",
+                    chars.encoding(),
+                    self.encoding()
+                ),
+            ));
         }
-        let r = unsafe {
-            let start = beg.add(from);
-            let range = beg.add(to);
-            if start > end {
-                return Err(Error::custom("Start of match should be before end"));
-            }
-            if range > end {
-                return Err(Error::custom("Limit of match should be before end"));
+        match_param.check_aborted()?;
+        let (start, range) = unsafe { (beg.add(from), beg.add(to)) };
+        if start > end {
+            return Err(Error::custom(
+                ErrorKind::InvalidArgument,
+                "Start of match should be before end",
+            ));
+        }
+        if range > end {
+            return Err(Error::custom(
+                ErrorKind::InvalidArgument,
+                "Limit of match should be before end",
+            ));
+        }
+        // The each match callback is given the region, so it needs one
+        // even if the caller doesn't.
+        let mut each_match_region = Region::new();
+        let region = match region {
+            Some(region) => region as *mut Region as *mut onig_sys::OnigRegion,
+            None if options.contains(SearchOptions::SEARCH_OPTION_CALLBACK_EACH_MATCH) => {
+                &mut each_match_region as *mut Region as *mut onig_sys::OnigRegion
             }
+            None => std::ptr::null_mut(),
+        };
+        let r = match_param.search_in_slices(|| unsafe {
             onig_sys::onig_search_with_param(
-                self.raw,
+                self.as_raw(),
                 beg,
                 end,
                 start,
                 range,
-                match region {
-                    Some(region) => region as *mut Region as *mut onig_sys::OnigRegion,
-                    None => std::ptr::null_mut(),
-                },
+                region,
                 options.bits(),
                 match_param.as_raw(),
             )
-        };
+        });
+        callout::resume_callout_panic();
 
         if r >= 0 {
             Ok(Some(r as usize))
         } else if r == onig_sys::ONIG_MISMATCH {
             Ok(None)
         } else {
+            // Report why a deadline or cancellation aborted the search.
+            if r == onig_sys::ONIG_ABORT {
+                match_param.check_aborted()?;
+            }
             Err(Error::from_code(r))
         }
     }
@@ -814,6 +1200,162 @@ impl Regex {
         .and_then(|_| region.pos(0))
     }
 
+    /// Try to Find a Match in a String
+    ///
+    /// Finds the first match of the regular expression within
+    /// `text`, as `find` does. Rather than panicking if the engine
+    /// fails the search is reported as an `Error`. This allows limits
+    /// set on the `match_param`, such as the retry limit, to be told
+    /// apart from a failure to match.
+    ///
+    /// # Arguments
+    ///  * `text` - The text to search in.
+    ///  * `match_param` - The match parameters
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Some((start, end)))` with the offset of the start and end
+    /// of the first match. `Ok(None)` if no match exists. `Err` with
+    /// an `Error` if the search failed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{Regex, MatchParam};
+    ///
+    /// let r = Regex::new("(a|b|ab)*bc").unwrap();
+    /// let mut param = MatchParam::default();
+    /// param.set_retry_limit_in_match(100);
+    /// assert_eq!(r.try_search("abc", &param).unwrap(), Some((0, 3)));
+    /// assert!(r.try_search("abababababababababababacbc", &param).is_err());
+    /// ```
+    pub fn try_search(
+        &self,
+        text: &str,
+        match_param: &MatchParam,
+    ) -> Result<Option<(usize, usize)>, Error> {
+        let mut region = Region::new();
+        let r = self.search_raw(
+            text,
+            0,
+            text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+            match_param,
+        )?;
+        Ok(r.and_then(|_| region.pos(0)))
+    }
+
+    /// Try to Match a String
+    ///
+    /// Tries to match the regex against the start of `text`. Rather
+    /// than panicking if the engine fails the match is reported as an
+    /// `Error`.
+    ///
+    /// # Arguments
+    ///  * `text` - The text to match against.
+    ///  * `match_param` - The match parameters
+    ///
+    /// # Returns
+    ///
+    /// `Ok(Some(len))` if the regex matched, with `len` being the
+    /// number of bytes matched. `Ok(None)` if the regex doesn't
+    /// match. `Err` with an `Error` if the match failed.
+    pub fn try_match(&self, text: &str, match_param: &MatchParam) -> Result<Option<usize>, Error> {
+        self.match_raw(
+            text,
+            0,
+            SearchOptions::SEARCH_OPTION_NONE,
+            None,
+            match_param,
+        )
+    }
+
+    /// Wrap a compiled regex. `pattern` and `syntax` must be the
+    /// buffer and syntax the regex was compiled from.
+    pub(crate) fn from_raw(
+        raw: onig_sys::OnigRegex,
+        pattern: Box<[u8]>,
+        syntax: Box<Syntax>,
+    ) -> Regex {
+        Regex {
+            raw: Arc::new(RawRegex {
+                raw,
+                pattern,
+                syntax,
+            }),
+        }
+    }
+
+    pub(crate) fn as_raw(&self) -> onig_sys::OnigRegex {
+        self.raw.raw
+    }
+
+    /// Get the Pattern
+    ///
+    /// Returns the source pattern this regex was compiled from, or
+    /// `None` if the regex wasn't compiled from UTF-8 or ASCII, such as
+    /// a UTF-16 pattern given to `Regex::with_encoding`. `to_string`
+    /// can be used to show the pattern of any regex.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::Regex;
+    ///
+    /// let r = Regex::new(r"hello (\w+)").unwrap();
+    /// assert_eq!(r.as_str(), Some(r"hello (\w+)"));
+    /// assert_eq!(r.to_string(), r"hello (\w+)");
+    /// ```
+    pub fn as_str(&self) -> Option<&str> {
+        match Encoding::from_raw(self.encoding()) {
+            Some(Encoding::Utf8) | Some(Encoding::Ascii) => str::from_utf8(&self.raw.pattern).ok(),
+            _ => None,
+        }
+    }
+
+    /// Get the Options of the Regex
+    ///
+    /// Returns the options the regex was compiled with. This includes
+    /// any options implied by the syntax.
+    pub fn options(&self) -> RegexOptions {
+        RegexOptions::from_bits_truncate(unsafe { onig_sys::onig_get_options(self.as_raw()) })
+    }
+
+    /// Get the Syntax of the Regex
+    ///
+    /// Returns a copy of the syntax the regex was compiled with. Later
+    /// changes to the original syntax don't affect it.
+    pub fn syntax(&self) -> &Syntax {
+        unsafe { &*(onig_sys::onig_get_syntax(self.as_raw()) as *const Syntax) }
+    }
+
+    /// Get the Case Fold Flag of the Regex
+    ///
+    /// Returns the case folding flags used for case insensitive
+    /// matching.
+    pub fn case_fold_flag(&self) -> CaseFold {
+        CaseFold::from_bits_truncate(unsafe { onig_sys::onig_get_case_fold_flag(self.as_raw()) })
+    }
+
+    /// Do Unnamed Groups Capture?
+    ///
+    /// Returns true if the unnamed groups `(...)` in this regex
+    /// capture. This depends on the options and syntax, and whether
+    /// the pattern contains any named groups.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::Regex;
+    ///
+    /// assert!(Regex::new("(a)").unwrap().noname_group_capture_is_active());
+    /// assert!(!Regex::new("(?<n>a)(b)").unwrap().noname_group_capture_is_active());
+    /// ```
+    pub fn noname_group_capture_is_active(&self) -> bool {
+        unsafe { onig_sys::onig_noname_group_capture_is_active(self.as_raw()) != 0 }
+    }
+
     /// Get the Encoding of the Regex
     ///
     /// # Returns
@@ -821,21 +1363,59 @@ impl Regex {
     /// Returns a reference to an oniguruma encoding which was used
     /// when this regex was created.
     pub fn encoding(&self) -> onig_sys::OnigEncoding {
-        unsafe { onig_sys::onig_get_encoding(self.raw) }
+        unsafe { onig_sys::onig_get_encoding(self.as_raw()) }
     }
 
     /// Get the Number of Capture Groups in this Pattern
     pub fn captures_len(&self) -> usize {
-        unsafe { onig_sys::onig_number_of_captures(self.raw) as usize }
+        unsafe { onig_sys::onig_number_of_captures(self.as_raw()) as usize }
     }
 
     /// Get the Size of the Capture Histories for this Pattern
     pub fn capture_histories_len(&self) -> usize {
-        unsafe { onig_sys::onig_number_of_capture_histories(self.raw) as usize }
+        unsafe { onig_sys::onig_number_of_capture_histories(self.as_raw()) as usize }
+    }
+}
+
+/// Shows the pattern of the regex, decoded with its encoding. Patterns
+/// in encodings which aren't Unicode have their non-ASCII characters
+/// written as `\xHH` escapes.
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use std::fmt::Write;
+
+        let enc = self.encoding();
+        let unicode = Encoding::from_raw(enc).map(Encoding::is_unicode) == Some(true);
+        let pattern = &self.raw.pattern[..];
+        let mut at = 0;
+        while at < pattern.len() {
+            let len = encoding::char_len(enc, pattern, at);
+            let bytes = &pattern[at..at + len];
+            if unicode {
+                // Decode from a padded copy, as a truncated character
+                // at the end of the pattern could be read past.
+                let mut padded = [0u8; 8];
+                padded[..len.min(8)].copy_from_slice(&bytes[..len.min(8)]);
+                let code = unsafe {
+                    (*enc)
+                        .mbc_to_code
+                        .map(|f| f(padded.as_ptr(), padded.as_ptr().add(len.min(8))))
+                };
+                f.write_char(code.and_then(std::char::from_u32).unwrap_or('\u{fffd}'))?;
+            } else if len == 1 && bytes[0].is_ascii() {
+                f.write_char(bytes[0] as char)?;
+            } else {
+                for byte in bytes {
+                    write!(f, "\\x{:02x}", byte)?;
+                }
+            }
+            at += len;
+        }
+        Ok(())
     }
 }
 
-impl Drop for Regex {
+impl Drop for RawRegex {
     fn drop(&mut self) {
         unsafe {
             onig_sys::onig_free(self.raw);
@@ -862,6 +1442,128 @@ mod tests {
         assert_eq!(e.description(), "invalid character property name {foo}");
     }
 
+    #[test]
+    fn test_regex_error_kind() {
+        let e = Regex::new("\\p{foo}").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UndefinedName);
+        assert!(e.is_undefined_name());
+        assert!(!e.is_syntax());
+
+        let e = Regex::new("(?<foo>a)\\k<bar>").unwrap_err();
+        assert!(e.is_undefined_name());
+
+        let e = Regex::new("a(b").unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Syntax);
+        assert!(e.is_syntax());
+
+        let e = Regex::new("[b-a]").unwrap_err();
+        assert!(e.is_syntax());
+
+        let e = Regex::new("\\x{7fffffffff}").unwrap_err();
+        assert!(e.is_invalid_encoding());
+
+        for &code in &[
+            onig_sys::ONIGERR_INVALID_WIDE_CHAR_VALUE,
+            onig_sys::ONIGERR_TOO_BIG_WIDE_CHAR_VALUE,
+            onig_sys::ONIGERR_TOO_LONG_WIDE_CHAR_VALUE,
+        ] {
+            assert_eq!(ErrorKind::from_code(code), ErrorKind::InvalidEncoding);
+        }
+        assert_eq!(
+            ErrorKind::from_code(onig_sys::ONIGERR_TOO_LONG_PROPERTY_NAME),
+            ErrorKind::UndefinedName
+        );
+        assert_eq!(
+            ErrorKind::from_code(onig_sys::ONIG_NO_SUPPORT_CONFIG),
+            ErrorKind::Unsupported
+        );
+        assert_eq!(
+            ErrorKind::from_code(onig_sys::ONIGERR_INVALID_CALLOUT_ARG),
+            ErrorKind::Syntax
+        );
+        // Codes this crate doesn't know about aren't assumed to be
+        // syntax errors, even in the range Oniguruma uses for them.
+        assert_eq!(ErrorKind::from_code(-299), ErrorKind::Internal);
+        assert_eq!(ErrorKind::from_code(-107), ErrorKind::Internal);
+    }
+
+    #[test]
+    fn test_regex_error_span() {
+        let e = Regex::new("(?<foo>a)\\k<bar>").unwrap_err();
+        assert_eq!(e.pattern_span(), Some((12, 15)));
+        assert_eq!(
+            e.to_string(),
+            "Oniguruma error: undefined name <bar> reference\n    (?<foo>a)\\k<bar>\n                ^^^"
+        );
+
+        let e = Regex::new("a(b").unwrap_err();
+        assert_eq!(e.pattern_span(), None);
+        assert_eq!(
+            e.to_string(),
+            "Oniguruma error: end pattern with unmatched parenthesis"
+        );
+    }
+
+    #[test]
+    fn test_regex_error_span_not_on_char_boundary() {
+        let mut e = Error::custom(ErrorKind::Syntax, "bad");
+        e.pattern = Some("äb".to_owned());
+        e.span = Some((1, 2));
+        assert_eq!(e.to_string(), "Oniguruma error: bad");
+
+        e.span = Some((10, 12));
+        assert_eq!(e.to_string(), "Oniguruma error: bad");
+    }
+
+    #[test]
+    fn test_regex_error_span_multiline() {
+        let e = Regex::with_options(
+            "(?<ä>a)\n  \\g<b>",
+            RegexOptions::REGEX_OPTION_EXTEND,
+            Syntax::default(),
+        )
+        .unwrap_err();
+        assert_eq!(e.pattern_span(), Some((14, 15)));
+        assert!(e.to_string().ends_with("\n      \\g<b>\n         ^"));
+    }
+
+    #[test]
+    fn test_search_error_kind() {
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let e = regex
+            .try_search(
+                "ababababababababababababababababababababababababababababacbc",
+                &MatchParam::default(),
+            )
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::LimitExceeded);
+        assert!(e.is_limit_exceeded());
+
+        let ascii = EncodedBytes::ascii(b"hello");
+        let e = regex
+            .search_with_param(
+                ascii,
+                0,
+                5,
+                SearchOptions::SEARCH_OPTION_NONE,
+                None,
+                MatchParam::default(),
+            )
+            .unwrap_err();
+        assert!(e.is_invalid_encoding());
+
+        let e = regex
+            .match_with_param(
+                "abc",
+                10,
+                SearchOptions::SEARCH_OPTION_NONE,
+                None,
+                MatchParam::default(),
+            )
+            .unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    }
+
     #[test]
     fn test_failed_match() {
         let regex = Regex::new("foo").unwrap();
@@ -938,6 +1640,85 @@ mod tests {
         assert_eq!(regex.find("hey, honey!"), None);
     }
 
+    #[test]
+    fn test_regex_source() {
+        let regex = Regex::new("(he)(l+)(o)").unwrap();
+        assert_eq!(regex.as_str(), Some("(he)(l+)(o)"));
+        assert_eq!(format!("<{}>", regex), "<(he)(l+)(o)>");
+
+        let regex =
+            Regex::with_encoding(EncodedBytes::new(b"caf\xe9", Encoding::Iso8859_1)).unwrap();
+        assert_eq!(regex.as_str(), None);
+        assert_eq!(regex.to_string(), "café");
+
+        let regex = Regex::with_encoding(EncodedBytes::new(b"l\0+\0", Encoding::Utf16Le)).unwrap();
+        assert_eq!(regex.as_str(), None);
+        assert_eq!(regex.to_string(), "l+");
+
+        let regex =
+            Regex::with_encoding(EncodedBytes::new(b"\x83\x41+", Encoding::ShiftJis)).unwrap();
+        assert_eq!(regex.as_str(), None);
+        assert_eq!(regex.to_string(), "\\x83\\x41+");
+    }
+
+    #[test]
+    fn test_regex_settings() {
+        let regex = Regex::with_options(
+            "a b",
+            RegexOptions::REGEX_OPTION_IGNORECASE | RegexOptions::REGEX_OPTION_EXTEND,
+            Syntax::perl(),
+        )
+        .unwrap();
+        assert!(regex
+            .options()
+            .contains(RegexOptions::REGEX_OPTION_IGNORECASE | RegexOptions::REGEX_OPTION_EXTEND));
+        assert_eq!(regex.syntax().operators(), Syntax::perl().operators());
+        assert_eq!(regex.case_fold_flag(), defaults::case_fold_flag());
+    }
+
+    #[test]
+    fn test_regex_syntax_is_copied() {
+        let mut syntax = *Syntax::ruby();
+        syntax.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_PLUS_ONE_INF);
+        let regex = Regex::with_options("a+", RegexOptions::REGEX_OPTION_NONE, &syntax).unwrap();
+        syntax.enable_operators(SyntaxOperator::SYNTAX_OPERATOR_PLUS_ONE_INF);
+        drop(syntax);
+        assert!(!regex
+            .syntax()
+            .operators()
+            .contains(SyntaxOperator::SYNTAX_OPERATOR_PLUS_ONE_INF));
+        assert_eq!(regex.find("aa+"), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_regex_clone() {
+        let regexes = vec![
+            Regex::new("h(e)llo").unwrap(),
+            Regex::with_options(
+                "H(E)LLO",
+                RegexOptions::REGEX_OPTION_IGNORECASE,
+                Syntax::perl(),
+            )
+            .unwrap(),
+            Regex::with_encoding(EncodedBytes::ascii(b"h(e)llo")).unwrap(),
+            Regex::with_options_and_encoding(
+                EncodedBytes::ascii(b"h(e)llo"),
+                RegexOptions::REGEX_OPTION_NONE,
+                Syntax::default(),
+            )
+            .unwrap(),
+            RegexBuilder::new("h(e)llo").build().unwrap(),
+        ];
+        for regex in regexes {
+            let clone = regex.clone();
+            assert_eq!(clone, regex);
+            drop(regex);
+            assert_eq!(clone.captures_len(), 1);
+            let handle = std::thread::spawn(move || bytes::Regex::from(clone).find(b"oh hello"));
+            assert_eq!(handle.join().unwrap(), Some((3, 8)));
+        }
+    }
+
     #[test]
     fn test_regex_captures_len() {
         let regex = Regex::new("(he)(l+)(o)").unwrap();
@@ -1004,6 +1785,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_regex_try_search() {
+        let regex = Regex::new("he(l+)o").unwrap();
+        let param = MatchParam::default();
+        assert_eq!(
+            regex.try_search("hey, hello!", &param).unwrap(),
+            Some((5, 10))
+        );
+        assert_eq!(regex.try_search("hey, honey!", &param).unwrap(), None);
+    }
+
+    #[test]
+    fn test_regex_try_search_error() {
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let result = regex.try_search(
+            "ababababababababababababababababababababababababababababacbc",
+            &MatchParam::default(),
+        );
+        let e = result.err().unwrap();
+        assert_eq!("retry-limit-in-match over", e.description());
+    }
+
+    #[test]
+    fn test_regex_try_match() {
+        let regex = Regex::new("he(l+)").unwrap();
+        let param = MatchParam::default();
+        assert_eq!(regex.try_match("hello", &param).unwrap(), Some(4));
+        assert_eq!(regex.try_match("oh hello", &param).unwrap(), None);
+
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let mut param = MatchParam::default();
+        param.set_retry_limit_in_match(10);
+        assert!(regex.try_match("abababababababacbc", &param).is_err());
+    }
+
     #[test]
     fn test_search_with_invalid_range() {
         let regex = Regex::with_options("R...", RegexOptions::REGEX_OPTION_NONE, Syntax::default())
@@ -0,0 +1,163 @@
+//! Process-wide Limits
+//!
+//! Oniguruma keeps a number of limits as global state which applies
+//! to every regex in the process. This module provides safe access to
+//! them. Limits which apply when compiling a pattern can be
+//! overridden for a single regex with `RegexBuilder`, and limits which
+//! apply when searching can be overridden for a single search with
+//! `MatchParam`.
+//!
+//! Setters for limits used while compiling take the same lock as
+//! regex compilation, so they never race with a regex being built.
+//!
+//! # Examples
+//!
+//! ```
+//! use onig::limits;
+//!
+//! let depth = limits::parse_depth_limit();
+//! limits::set_parse_depth_limit(depth + 1).unwrap();
+//! assert_eq!(limits::parse_depth_limit(), depth + 1);
+//!
+//! limits::set_retry_limit_in_search(1 << 30).unwrap();
+//! assert_eq!(limits::retry_limit_in_search(), 1 << 30);
+//!
+//! let captures = limits::capture_num_limit();
+//! limits::set_capture_num_limit(captures).unwrap();
+//! assert_eq!(limits::capture_num_limit(), captures);
+//!
+//! limits::set_subexp_call_limit_in_search(1 << 30).unwrap();
+//! assert_eq!(limits::subexp_call_limit_in_search(), 1 << 30);
+//!
+//! let nest = limits::subexp_call_max_nest_level();
+//! limits::set_subexp_call_max_nest_level(nest + 1).unwrap();
+//! assert_eq!(limits::subexp_call_max_nest_level(), nest + 1);
+//! ```
+
+use std::convert::TryFrom;
+use std::os::raw::{c_int, c_ulong};
+use std::sync::atomic::Ordering;
+
+use super::builder::CAPTURE_NUM_LIMIT;
+use super::{Error, ErrorKind, REGEX_NEW_MUTEX};
+
+/// Get the Retry Limit in Search
+///
+/// The maximum number of retries over all of the match attempts made
+/// by a single search. `0` means there is no limit.
+pub fn retry_limit_in_search() -> u64 {
+    unsafe { onig_sys::onig_get_retry_limit_in_search() as u64 }
+}
+
+/// Set the Retry Limit in Search
+///
+/// Sets the default retry limit in search for all searches which
+/// don't override it with `MatchParam::set_retry_limit_in_search`.
+pub fn set_retry_limit_in_search(limit: u64) -> Result<(), Error> {
+    let limit = to_raw::<c_ulong, _>(limit)?;
+    check(unsafe { onig_sys::onig_set_retry_limit_in_search(limit) })
+}
+
+/// Get the Parse Depth Limit
+///
+/// The maximum nesting depth of a pattern when it is compiled.
+pub fn parse_depth_limit() -> u32 {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    unsafe { onig_sys::onig_get_parse_depth_limit() }
+}
+
+/// Set the Parse Depth Limit
+///
+/// Sets the default parse depth limit for all regexes compiled
+/// without `RegexBuilder::parse_depth_limit`. A limit of `0` restores
+/// Oniguruma's built in default.
+pub fn set_parse_depth_limit(limit: u32) -> Result<(), Error> {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    check(unsafe { onig_sys::onig_set_parse_depth_limit(limit) })
+}
+
+/// Get the Capture Number Limit
+///
+/// The maximum number of capture groups a pattern may contain.
+pub fn capture_num_limit() -> usize {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    CAPTURE_NUM_LIMIT.load(Ordering::SeqCst) as usize
+}
+
+/// Set the Capture Number Limit
+///
+/// Sets the default capture number limit for all regexes compiled
+/// without `RegexBuilder::capture_limit`.
+pub fn set_capture_num_limit(limit: usize) -> Result<(), Error> {
+    let limit = to_raw::<c_int, _>(limit)?;
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    check(unsafe { onig_sys::onig_set_capture_num_limit(limit) })?;
+    CAPTURE_NUM_LIMIT.store(limit, Ordering::SeqCst);
+    Ok(())
+}
+
+/// Get the Subexpression Call Limit in Search
+///
+/// The maximum number of subexpression calls, such as `\g<name>`,
+/// made by a single search. `0` means there is no limit.
+pub fn subexp_call_limit_in_search() -> u64 {
+    unsafe { onig_sys::onig_get_subexp_call_limit_in_search() as u64 }
+}
+
+/// Set the Subexpression Call Limit in Search
+pub fn set_subexp_call_limit_in_search(limit: u64) -> Result<(), Error> {
+    let limit = to_raw::<c_ulong, _>(limit)?;
+    check(unsafe { onig_sys::onig_set_subexp_call_limit_in_search(limit) })
+}
+
+/// Get the Subexpression Call Max Nest Level
+///
+/// The maximum depth to which subexpression calls may be nested
+/// within a pattern when it is compiled.
+pub fn subexp_call_max_nest_level() -> u32 {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    unsafe { onig_sys::onig_get_subexp_call_max_nest_level() as u32 }
+}
+
+/// Set the Subexpression Call Max Nest Level
+pub fn set_subexp_call_max_nest_level(level: u32) -> Result<(), Error> {
+    let level = to_raw::<c_int, _>(level)?;
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    check(unsafe { onig_sys::onig_set_subexp_call_max_nest_level(level) })
+}
+
+/// Convert a limit to the type Oniguruma expects, or fail if it is
+/// too large.
+fn to_raw<R: TryFrom<T>, T: Copy + std::fmt::Display>(limit: T) -> Result<R, Error> {
+    R::try_from(limit).map_err(|_| {
+        Error::custom(
+            ErrorKind::InvalidArgument,
+            format!("Limit {} is too large", limit),
+        )
+    })
+}
+
+fn check(r: c_int) -> Result<(), Error> {
+    if r == onig_sys::ONIG_NORMAL as c_int {
+        Ok(())
+    } else {
+        Err(Error::from_code(r))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    // Setting the limits changes them for the tests running at the
+    // same time, so that is covered by the documentation example,
+    // which runs in its own process.
+
+    #[test]
+    fn test_limits_too_large() {
+        let e = limits::set_capture_num_limit(usize::MAX).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+        let e = limits::set_subexp_call_max_nest_level(u32::MAX).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidArgument);
+    }
+}
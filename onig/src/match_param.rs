@@ -3,11 +3,55 @@
 //! Contains the definition for the `MatchParam` struct. This can be
 //! used to control the behavior of searching and matching.
 
-use std::os::raw::{c_uint, c_ulong};
+use std::cell::RefCell;
+use std::os::raw::{c_int, c_uint, c_ulong, c_void};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Once};
+use std::time::Instant;
+
+use super::callout::catch_callout_panic;
+use super::{CalloutArgs, CalloutResult, Error, ErrorKind, Region};
+
+type MatchCallout = RefCell<Option<Box<dyn FnMut(&CalloutArgs) -> CalloutResult>>>;
+
+type EachMatchCallback = RefCell<Option<Box<dyn FnMut(&Region)>>>;
+
+/// The retry limit for the first slice of a search with a deadline or
+/// cancellation token. Each later slice is allowed twice as many.
+const FIRST_SLICE_RETRIES: c_ulong = 10_000;
 
 /// Parameters for a Match or Search.
 pub struct MatchParam {
     raw: *mut onig_sys::OnigMatchParam,
+    callouts: Box<MatchCallouts>,
+}
+
+/// The callouts set on a `MatchParam`. Boxed so that its address can
+/// be passed to Oniguruma as the callout user data.
+#[derive(Default)]
+struct MatchCallouts {
+    progress: MatchCallout,
+    retraction: MatchCallout,
+    each_match: EachMatchCallback,
+    deadline: Option<Instant>,
+    cancel_token: Option<Arc<AtomicBool>>,
+    retry_limit_in_search: c_ulong,
+}
+
+impl MatchCallouts {
+    /// Check the deadline and cancellation token, returning the
+    /// reason the search should be aborted, if any.
+    fn abort_reason(&self) -> Option<&'static str> {
+        if let Some(token) = &self.cancel_token {
+            if token.load(Ordering::Relaxed) {
+                return Some("Search was cancelled");
+            }
+        }
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Some("Search deadline exceeded"),
+            _ => None,
+        }
+    }
 }
 
 impl MatchParam {
@@ -25,6 +69,261 @@ impl MatchParam {
         }
     }
 
+    /// Set the retry limit in search
+    ///
+    /// Limits the total number of retries over all of the match
+    /// attempts made by a single search. A limit of `0` means no
+    /// limit.
+    pub fn set_retry_limit_in_search(&mut self, limit: u32) {
+        self.callouts.retry_limit_in_search = c_ulong::from(limit);
+        unsafe {
+            onig_sys::onig_set_retry_limit_in_search_of_match_param(self.raw, c_ulong::from(limit));
+        }
+    }
+
+    /// Set the Progress Callout
+    ///
+    /// The `callout` is called whenever the matcher reaches a contents
+    /// callout, `(?{...})`, in the pattern during searches which use
+    /// this `MatchParam`. Contents callouts are only available in
+    /// syntaxes which support them, such as `Syntax::perl()`.
+    ///
+    /// If the callout panics the search is abandoned, and the panic
+    /// carries on from the search method once Oniguruma has returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{CalloutResult, MatchParam, Regex, RegexOptions, Syntax};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let regex = Regex::with_options(
+    ///     "a(?{seen_a})b",
+    ///     RegexOptions::REGEX_OPTION_NONE,
+    ///     Syntax::perl(),
+    /// )
+    /// .unwrap();
+    ///
+    /// let trace = Rc::new(RefCell::new(Vec::new()));
+    /// let mut param = MatchParam::default();
+    /// let recorder = trace.clone();
+    /// param.set_progress_callout(move |args| {
+    ///     recorder.borrow_mut().push((args.contents().unwrap().to_vec(), args.position()));
+    ///     CalloutResult::Continue
+    /// });
+    ///
+    /// assert_eq!(regex.try_search("xab", &param).unwrap(), Some((1, 3)));
+    /// assert_eq!(*trace.borrow(), vec![(b"seen_a".to_vec(), 2)]);
+    /// ```
+    pub fn set_progress_callout<F>(&mut self, callout: F)
+    where
+        F: FnMut(&CalloutArgs) -> CalloutResult + 'static,
+    {
+        *self.callouts.progress.borrow_mut() = Some(Box::new(callout));
+        unsafe {
+            onig_sys::onig_set_progress_callout_of_match_param(self.raw, Some(progress_callout));
+        }
+    }
+
+    /// Set the Retraction Callout
+    ///
+    /// The `callout` is called whenever the matcher backtracks over a
+    /// contents callout which is marked to be called on retraction,
+    /// `(?{...}<)` or `(?{...}X)`, during searches which use this
+    /// `MatchParam`. Panics are handled as for `set_progress_callout`.
+    pub fn set_retraction_callout<F>(&mut self, callout: F)
+    where
+        F: FnMut(&CalloutArgs) -> CalloutResult + 'static,
+    {
+        *self.callouts.retraction.borrow_mut() = Some(Box::new(callout));
+        unsafe {
+            onig_sys::onig_set_retraction_callout_of_match_param(
+                self.raw,
+                Some(retraction_callout),
+            );
+        }
+    }
+
+    /// Set the Each Match Callback
+    ///
+    /// The `callback` is called with the captures of every match found
+    /// by searches which use this `MatchParam` and the
+    /// `SEARCH_OPTION_CALLBACK_EACH_MATCH` option. With that option the
+    /// search carries on after each match, and doesn't return a match
+    /// itself. Region positions are byte offsets into the string.
+    ///
+    /// Oniguruma has a single, process-wide each match callback. The
+    /// first call to this method installs one which dispatches to the
+    /// closure of the `MatchParam` used by each search, replacing any
+    /// set with `onig_sys::onig_set_callback_each_match`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{MatchParam, Regex, SearchOptions};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let regex = Regex::new("a").unwrap();
+    /// let matches = Rc::new(RefCell::new(Vec::new()));
+    /// let mut param = MatchParam::default();
+    /// let recorder = matches.clone();
+    /// param.set_each_match_callback(move |region| recorder.borrow_mut().push(region.pos(0)));
+    ///
+    /// let r = regex
+    ///     .search_with_param(
+    ///         "banana",
+    ///         0,
+    ///         6,
+    ///         SearchOptions::SEARCH_OPTION_CALLBACK_EACH_MATCH,
+    ///         None,
+    ///         param,
+    ///     )
+    ///     .unwrap();
+    /// assert_eq!(r, None);
+    /// assert_eq!(
+    ///     *matches.borrow(),
+    ///     vec![Some((1, 2)), Some((3, 4)), Some((5, 6))]
+    /// );
+    /// ```
+    pub fn set_each_match_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(&Region) + 'static,
+    {
+        static INSTALLED: Once = Once::new();
+        INSTALLED.call_once(|| unsafe {
+            onig_sys::onig_set_callback_each_match(Some(each_match_callback));
+        });
+        *self.callouts.each_match.borrow_mut() = Some(Box::new(callback));
+    }
+
+    /// Set a Deadline for the Search
+    ///
+    /// Searches using this `MatchParam` are aborted with an
+    /// `ErrorKind::Aborted` error once `deadline` has passed.
+    ///
+    /// Oniguruma has no clock of its own, so a search with a deadline
+    /// is run in slices. Each slice is stopped by the retry limit, and
+    /// the deadline is checked before the search is started again with
+    /// twice the limit. Searches which don't backtrack much finish in
+    /// the first slice. The deadline is also checked each time the
+    /// matcher reaches a callout in the pattern.
+    ///
+    /// Because the search is restarted, the work of an aborted search
+    /// can run past the deadline by up to the time already spent, and
+    /// callouts may be called again for the parts of the text which are
+    /// searched more than once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{ErrorKind, MatchParam, Regex};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let regex = Regex::new("(a|b|ab)*bc").unwrap();
+    ///
+    /// let mut param = MatchParam::default();
+    /// param.set_retry_limit_in_match(u32::MAX);
+    /// param.set_deadline(Instant::now() + Duration::from_millis(10));
+    /// let text = format!("{}acbc", "ab".repeat(40));
+    /// let e = regex.try_search(&text, &param).unwrap_err();
+    /// assert_eq!(e.kind(), ErrorKind::Aborted);
+    /// ```
+    ///
+    /// The retry limit still applies to searches with a deadline,
+    /// including the process-wide default from `onig::limits`:
+    ///
+    /// ```
+    /// use onig::{limits, MatchParam, Regex};
+    /// use std::time::{Duration, Instant};
+    ///
+    /// let regex = Regex::new("(a|b|ab)*bc").unwrap();
+    ///
+    /// limits::set_retry_limit_in_search(10).unwrap();
+    /// let mut param = MatchParam::default();
+    /// param.set_deadline(Instant::now() + Duration::from_secs(60));
+    /// let e = regex.try_search("abababababababacbc", &param).unwrap_err();
+    /// assert!(e.is_limit_exceeded());
+    /// ```
+    pub fn set_deadline(&mut self, deadline: Instant) {
+        self.callouts.deadline = Some(deadline);
+        self.enable_abort_checks();
+    }
+
+    /// Set a Cancellation Token for the Search
+    ///
+    /// Searches using this `MatchParam` are aborted with an
+    /// `ErrorKind::Aborted` error once `token` is set to `true`. The
+    /// token can be set from another thread. It is checked at the same
+    /// points as the deadline, see `set_deadline`.
+    pub fn set_cancel_token(&mut self, token: Arc<AtomicBool>) {
+        self.callouts.cancel_token = Some(token);
+        self.enable_abort_checks();
+    }
+
+    /// Install the callout trampolines so that the deadline and
+    /// cancellation token are checked even if no closures are set.
+    fn enable_abort_checks(&mut self) {
+        unsafe {
+            onig_sys::onig_set_progress_callout_of_match_param(self.raw, Some(progress_callout));
+            onig_sys::onig_set_retraction_callout_of_match_param(
+                self.raw,
+                Some(retraction_callout),
+            );
+        }
+    }
+
+    /// Run a Search in Slices
+    ///
+    /// Calls `search` with a growing retry limit until it finishes or
+    /// the deadline or cancellation token fires, if either is set.
+    /// Otherwise `search` is called once. Returns the result of the
+    /// last call, or `ONIG_ABORT` if the search was abandoned.
+    pub(crate) fn search_in_slices<F>(&self, mut search: F) -> c_int
+    where
+        F: FnMut() -> c_int,
+    {
+        if self.callouts.deadline.is_none() && self.callouts.cancel_token.is_none() {
+            return search();
+        }
+        let limit = self.callouts.retry_limit_in_search;
+        let mut slice = FIRST_SLICE_RETRIES;
+        let r = loop {
+            // Once a slice would be allowed more than the caller's own
+            // limit the caller's limit is used, and its result stands.
+            let last = limit != 0 && slice >= limit;
+            self.set_raw_retry_limit_in_search(if last { limit } else { slice });
+            let r = search();
+            if last || r != onig_sys::ONIGERR_RETRY_LIMIT_IN_SEARCH_OVER {
+                break r;
+            }
+            if self.callouts.abort_reason().is_some() {
+                break onig_sys::ONIG_ABORT;
+            }
+            slice = slice.saturating_mul(2);
+        };
+        self.set_raw_retry_limit_in_search(limit);
+        r
+    }
+
+    fn set_raw_retry_limit_in_search(&self, limit: c_ulong) {
+        unsafe {
+            onig_sys::onig_set_retry_limit_in_search_of_match_param(self.raw, limit);
+        }
+    }
+
+    /// Check if the Search Should be Aborted
+    ///
+    /// Returns an `ErrorKind::Aborted` error if the deadline has
+    /// passed or the search has been cancelled.
+    pub(crate) fn check_aborted(&self) -> Result<(), Error> {
+        match self.callouts.abort_reason() {
+            Some(reason) => Err(Error::custom(ErrorKind::Aborted, reason)),
+            None => Ok(()),
+        }
+    }
+
     /// Get the Raw `OnigMatchParam` Pointer
     pub fn as_raw(&self) -> *mut onig_sys::OnigMatchParam {
         self.raw
@@ -33,12 +332,91 @@ impl MatchParam {
 
 impl Default for MatchParam {
     fn default() -> Self {
+        // `onig_initialize_match_param` starts from the process-wide
+        // retry limit, so the search slicing does too.
+        let callouts = Box::new(MatchCallouts {
+            retry_limit_in_search: unsafe { onig_sys::onig_get_retry_limit_in_search() },
+            ..MatchCallouts::default()
+        });
         let raw = unsafe {
             let new = onig_sys::onig_new_match_param();
             onig_sys::onig_initialize_match_param(new);
+            onig_sys::onig_set_callout_user_data_of_match_param(
+                new,
+                &*callouts as *const MatchCallouts as *mut c_void,
+            );
             new
         };
-        MatchParam { raw }
+        MatchParam { raw, callouts }
+    }
+}
+
+unsafe extern "C" fn progress_callout(
+    args: *mut onig_sys::OnigCalloutArgs,
+    user_data: *mut c_void,
+) -> c_int {
+    let callouts = &*(user_data as *const MatchCallouts);
+    call_match_callout(callouts, &callouts.progress, args)
+}
+
+unsafe extern "C" fn retraction_callout(
+    args: *mut onig_sys::OnigCalloutArgs,
+    user_data: *mut c_void,
+) -> c_int {
+    let callouts = &*(user_data as *const MatchCallouts);
+    call_match_callout(callouts, &callouts.retraction, args)
+}
+
+unsafe extern "C" fn each_match_callback(
+    _str: *const onig_sys::OnigUChar,
+    _end: *const onig_sys::OnigUChar,
+    _match_start: *const onig_sys::OnigUChar,
+    region: *mut onig_sys::OnigRegion,
+    user_data: *mut c_void,
+) -> c_int {
+    if user_data.is_null() {
+        return onig_sys::ONIG_NORMAL as c_int;
+    }
+    let callouts = &*(user_data as *const MatchCallouts);
+    match callouts.each_match.try_borrow_mut() {
+        Ok(mut callback) => match callback.as_mut() {
+            Some(callback) => catch_callout_panic(|| {
+                callback(&Region::clone_from_raw(region));
+                onig_sys::ONIG_NORMAL as c_int
+            }),
+            None => onig_sys::ONIG_NORMAL as c_int,
+        },
+        Err(_) => onig_sys::ONIG_ABORT,
+    }
+}
+
+/// Check the Deadline and Cancellation Token from a Callout
+///
+/// `user_data` is the callout user data of the search. This is null
+/// if the search isn't using a `MatchParam` from this crate.
+pub(crate) unsafe fn abort_requested(user_data: *mut c_void) -> bool {
+    !user_data.is_null()
+        && (*(user_data as *const MatchCallouts))
+            .abort_reason()
+            .is_some()
+}
+
+unsafe fn call_match_callout(
+    callouts: &MatchCallouts,
+    callout: &MatchCallout,
+    args: *mut onig_sys::OnigCalloutArgs,
+) -> c_int {
+    if callouts.abort_reason().is_some() {
+        return CalloutResult::Error.as_raw();
+    }
+    // If the callout is already running then a nested search is
+    // re-using this `MatchParam`. Abort rather than alias it.
+    match callout.try_borrow_mut() {
+        Ok(mut callout) => match callout.as_mut() {
+            Some(callout) => catch_callout_panic(|| callout(&CalloutArgs::from_raw(args)).as_raw()),
+            None => CalloutResult::Continue.as_raw(),
+        },
+        Err(_) => CalloutResult::Error.as_raw(),
     }
 }
 
@@ -71,4 +449,194 @@ mod test {
         let mut mp = MatchParam::default();
         mp.set_retry_limit_in_match(1000);
     }
+
+    #[test]
+    pub fn set_retry_limit_in_search() {
+        use super::super::Regex;
+
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_retry_limit_in_search(10);
+        let e = regex.try_search("abababababababacbc", &mp).unwrap_err();
+        assert!(e.is_limit_exceeded());
+        assert_eq!(e.code(), onig_sys::ONIGERR_RETRY_LIMIT_IN_SEARCH_OVER);
+    }
+
+    #[test]
+    pub fn set_progress_callout() {
+        use super::super::{Regex, RegexOptions, Syntax};
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        let regex = Regex::with_options(
+            "(?{first})a(?{second})",
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::perl(),
+        )
+        .unwrap();
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let mut mp = MatchParam::default();
+        let recorder = seen.clone();
+        mp.set_progress_callout(move |args| {
+            assert!(!args.is_retraction());
+            recorder
+                .borrow_mut()
+                .push(String::from_utf8_lossy(args.contents().unwrap()).into_owned());
+            CalloutResult::Continue
+        });
+        assert_eq!(regex.try_search("ba", &mp).unwrap(), Some((1, 2)));
+        assert_eq!(seen.borrow().last().unwrap(), "second");
+        assert!(seen.borrow().contains(&"first".to_string()));
+    }
+
+    #[test]
+    pub fn progress_callout_fail() {
+        use super::super::{Regex, RegexOptions, Syntax};
+
+        let regex = Regex::with_options("a(?{x})", RegexOptions::REGEX_OPTION_NONE, Syntax::perl())
+            .unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_progress_callout(|args| {
+            if args.position() < 3 {
+                CalloutResult::Fail
+            } else {
+                CalloutResult::Continue
+            }
+        });
+        assert_eq!(regex.try_search("aaa", &mp).unwrap(), Some((2, 3)));
+    }
+
+    #[test]
+    pub fn set_retraction_callout() {
+        use super::super::{Regex, RegexOptions, Syntax};
+        use std::cell::Cell;
+        use std::rc::Rc;
+
+        let regex = Regex::with_options(
+            "a(?{back}<)c|ab",
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::perl(),
+        )
+        .unwrap();
+        let retractions = Rc::new(Cell::new(0));
+        let mut mp = MatchParam::default();
+        let counter = retractions.clone();
+        mp.set_retraction_callout(move |args| {
+            assert!(args.is_retraction());
+            assert_eq!(args.contents(), Some(&b"back"[..]));
+            counter.set(counter.get() + 1);
+            CalloutResult::Continue
+        });
+        assert_eq!(regex.try_search("ab", &mp).unwrap(), Some((0, 2)));
+        assert_eq!(retractions.get(), 1);
+    }
+
+    #[test]
+    pub fn progress_callout_panic() {
+        use super::super::{Regex, RegexOptions, Syntax};
+        use std::panic::{self, AssertUnwindSafe};
+
+        let regex = Regex::with_options("a(?{x})", RegexOptions::REGEX_OPTION_NONE, Syntax::perl())
+            .unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_progress_callout(|_| panic!("progress callout panicked"));
+        let payload =
+            panic::catch_unwind(AssertUnwindSafe(|| regex.try_search("a", &mp))).unwrap_err();
+        assert_eq!(
+            payload.downcast_ref::<&str>(),
+            Some(&"progress callout panicked")
+        );
+
+        // The callout can still be replaced and used afterwards.
+        mp.set_progress_callout(|_| CalloutResult::Continue);
+        assert_eq!(regex.try_search("a", &mp).unwrap(), Some((0, 1)));
+    }
+
+    #[test]
+    pub fn deadline_aborts_search() {
+        use super::super::{Regex, RegexOptions, Syntax};
+        use std::time::Duration;
+
+        let regex = Regex::with_options(
+            "(?:a(?{})|a)*b",
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::perl(),
+        )
+        .unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_deadline(Instant::now() + Duration::from_millis(20));
+        let text = format!("{}!b", "a".repeat(64));
+        let e = regex.try_search(&text, &mp).unwrap_err();
+        assert!(e.is_aborted());
+        assert_eq!(e.description(), "Search deadline exceeded");
+
+        // A deadline in the past stops the search before it starts.
+        let regex = Regex::new("a").unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_deadline(Instant::now());
+        assert!(regex.try_search("a", &mp).unwrap_err().is_aborted());
+    }
+
+    #[test]
+    pub fn deadline_aborts_search_without_callouts() {
+        use super::super::Regex;
+        use std::time::Duration;
+
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let text = format!("{}acbc", "ab".repeat(40));
+        let mut mp = MatchParam::default();
+        mp.set_retry_limit_in_match(u32::MAX);
+        mp.set_deadline(Instant::now() + Duration::from_millis(20));
+        let started = Instant::now();
+        let e = regex.try_search(&text, &mp).unwrap_err();
+        assert!(e.is_aborted());
+        assert_eq!(e.description(), "Search deadline exceeded");
+        assert!(started.elapsed() < Duration::from_secs(5));
+
+        // Searches which finish aren't affected by the slicing.
+        let mut mp = MatchParam::default();
+        mp.set_deadline(Instant::now() + Duration::from_secs(60));
+        let text = format!("{}bc", "ab".repeat(40));
+        assert_eq!(regex.try_search(&text, &mp).unwrap(), Some((0, 82)));
+    }
+
+    #[test]
+    pub fn deadline_keeps_retry_limit() {
+        use super::super::Regex;
+        use std::time::Duration;
+
+        let regex = Regex::new("(a|b|ab)*bc").unwrap();
+        let mut mp = MatchParam::default();
+        mp.set_retry_limit_in_search(10);
+        mp.set_deadline(Instant::now() + Duration::from_secs(60));
+        let e = regex.try_search("abababababababacbc", &mp).unwrap_err();
+        assert_eq!(e.code(), onig_sys::ONIGERR_RETRY_LIMIT_IN_SEARCH_OVER);
+    }
+
+    #[test]
+    pub fn cancel_token_aborts_search() {
+        use super::super::{Regex, RegexOptions, Syntax};
+
+        let regex = Regex::with_options(
+            "a(?{})b(?{})",
+            RegexOptions::REGEX_OPTION_NONE,
+            Syntax::perl(),
+        )
+        .unwrap();
+        let token = Arc::new(AtomicBool::new(false));
+        let mut mp = MatchParam::default();
+        mp.set_cancel_token(token.clone());
+        assert_eq!(regex.try_search("ab", &mp).unwrap(), Some((0, 2)));
+
+        // Cancel from within the search, as another thread would.
+        let cancel = token.clone();
+        mp.set_progress_callout(move |_| {
+            cancel.store(true, Ordering::Relaxed);
+            CalloutResult::Continue
+        });
+        let e = regex.try_search("xabab", &mp).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::Aborted);
+        assert_eq!(e.description(), "Search was cancelled");
+        assert!(token.load(Ordering::Relaxed));
+    }
 }
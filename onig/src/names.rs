@@ -1,24 +1,113 @@
+use std::iter::FusedIterator;
 use std::os::raw::{c_int, c_void};
+use std::ptr::null_mut;
 use std::slice;
 use std::str::from_utf8_unchecked;
 
-use onig_sys::{OnigRegex, OnigUChar};
+use onig_sys::{OnigRegex, OnigRegion, OnigUChar};
 
-use super::Regex;
+use super::{RawRegex, Regex, Region};
 
 impl Regex {
     /// Returns the number of named groups into regex.
     pub fn capture_names_len(&self) -> usize {
-        unsafe { onig_sys::onig_number_of_names(self.raw) as usize }
+        unsafe { onig_sys::onig_number_of_names(self.as_raw()) as usize }
     }
 
     /// Calls `callback` for each named group in the regex. Each callback gets the group name
     /// and group indices.
-    pub fn foreach_name<F>(&self, mut callback: F) -> i32
+    pub fn foreach_name<F>(&self, callback: F) -> i32
     where
         F: FnMut(&str, &[u32]) -> bool,
     {
-        unsafe extern "C" fn foreach_cb<F>(
+        self.raw.foreach_name(callback)
+    }
+
+    /// Returns an iterator over the named groups in the regex. Each
+    /// item is the group name along with the numbers of the groups
+    /// which have that name.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use onig::Regex;
+    /// let re = Regex::new(r"(?<word>\w+)\s(?<num>\d+)|(?<word>x)").unwrap();
+    /// let names = re.capture_names().collect::<Vec<_>>();
+    /// assert_eq!(names, vec![("word", &[1, 3][..]), ("num", &[2][..])]);
+    /// ```
+    pub fn capture_names(&self) -> CaptureNames<'_> {
+        let mut names = Vec::with_capacity(self.capture_names_len());
+        self.raw.foreach_name(|name, groups| {
+            names.push((name, groups));
+            true
+        });
+        CaptureNames {
+            inner: names.into_iter(),
+        }
+    }
+
+    /// Returns the numbers of the groups called `name`, or `None` if
+    /// the regex has no group with that name.
+    pub fn group_numbers_for(&self, name: &str) -> Option<&[u32]> {
+        let mut nums: *mut c_int = null_mut();
+        let r = unsafe {
+            onig_sys::onig_name_to_group_numbers(
+                self.as_raw(),
+                name.as_ptr(),
+                name[name.len()..].as_ptr(),
+                &mut nums,
+            )
+        };
+        if r > 0 && !nums.is_null() {
+            Some(unsafe { slice::from_raw_parts(nums as *const u32, r as usize) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of the group a backreference to `name`
+    /// refers to, or `None` if the regex has no group with that name.
+    ///
+    /// If more than one group is called `name` then the last of those
+    /// groups which matched in `region` is returned. If none of them
+    /// matched the last group with the name is returned.
+    pub fn backref_number_for(&self, name: &str, region: &Region) -> Option<usize> {
+        self.raw.backref_number(name, Some(region))
+    }
+}
+
+/// An iterator over the named groups of a regex.
+///
+/// Each item holds a group name and the numbers of the groups with
+/// that name. `'r` is the lifetime of the `Regex`.
+pub struct CaptureNames<'r> {
+    inner: std::vec::IntoIter<(&'r str, &'r [u32])>,
+}
+
+impl<'r> Iterator for CaptureNames<'r> {
+    type Item = (&'r str, &'r [u32]);
+
+    fn next(&mut self) -> Option<(&'r str, &'r [u32])> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'r> FusedIterator for CaptureNames<'r> {}
+
+impl<'r> ExactSizeIterator for CaptureNames<'r> {}
+
+impl RawRegex {
+    /// Calls `callback` for each named group in the regex. The names
+    /// and group numbers borrow from the regex's name table.
+    pub(crate) fn foreach_name<'a, F>(&'a self, mut callback: F) -> i32
+    where
+        F: FnMut(&'a str, &'a [u32]) -> bool,
+    {
+        unsafe extern "C" fn foreach_cb<'a, F>(
             name: *const OnigUChar,
             name_end: *const OnigUChar,
             ngroup_num: c_int,
@@ -27,7 +116,7 @@ impl Regex {
             arg: *mut c_void,
         ) -> c_int
         where
-            F: FnMut(&str, &[u32]) -> bool,
+            F: FnMut(&'a str, &'a [u32]) -> bool,
         {
             let name = from_utf8_unchecked(slice::from_raw_parts(
                 name,
@@ -53,6 +142,28 @@ impl Regex {
             )
         }
     }
+
+    /// Looks up the group number a backreference to `name` refers to.
+    /// If more than one group has the name then the last one which
+    /// matched in `region` is chosen.
+    pub(crate) fn backref_number(&self, name: &str, region: Option<&Region>) -> Option<usize> {
+        let r = unsafe {
+            onig_sys::onig_name_to_backref_number(
+                self.raw,
+                name.as_ptr(),
+                name[name.len()..].as_ptr(),
+                match region {
+                    Some(region) => &region.raw as *const OnigRegion as *mut OnigRegion,
+                    None => std::ptr::null_mut(),
+                },
+            )
+        };
+        if r > 0 {
+            Some(r as usize)
+        } else {
+            None
+        }
+    }
 }
 
 #[cfg(test)]
@@ -88,4 +199,49 @@ mod tests {
             vec![("foo".into(), vec![1u32]), ("bar".into(), vec![2u32, 3])]
         );
     }
+
+    #[test]
+    fn test_regex_capture_names() {
+        let regex = Regex::new("(he)(l+)(o)").unwrap();
+        assert_eq!(regex.capture_names().count(), 0);
+        let regex = Regex::new("(?<foo>he)(?<bar>l+)(?<bar>o)").unwrap();
+        let names = regex.capture_names().collect::<Vec<_>>();
+        assert_eq!(names, vec![("foo", &[1][..]), ("bar", &[2, 3][..])]);
+    }
+
+    #[test]
+    fn test_regex_group_numbers_for() {
+        let regex = Regex::new("(?<foo>he)(?<bar>l+)(?<bar>o)").unwrap();
+        assert_eq!(regex.group_numbers_for("foo"), Some(&[1][..]));
+        assert_eq!(regex.group_numbers_for("bar"), Some(&[2, 3][..]));
+        assert_eq!(regex.group_numbers_for("baz"), None);
+    }
+
+    #[test]
+    fn test_regex_backref_number_for() {
+        let regex = Regex::new("(?<n>a)|(?<n>b)|(?<m>c)").unwrap();
+        let mut region = Region::new();
+        regex
+            .search_with_options(
+                "a",
+                0,
+                1,
+                SearchOptions::SEARCH_OPTION_NONE,
+                Some(&mut region),
+            )
+            .unwrap();
+        assert_eq!(regex.backref_number_for("n", &region), Some(1));
+        regex
+            .search_with_options(
+                "b",
+                0,
+                1,
+                SearchOptions::SEARCH_OPTION_NONE,
+                Some(&mut region),
+            )
+            .unwrap();
+        assert_eq!(regex.backref_number_for("n", &region), Some(2));
+        assert_eq!(regex.backref_number_for("m", &region), Some(3));
+        assert_eq!(regex.backref_number_for("x", &region), None);
+    }
 }
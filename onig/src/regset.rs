@@ -0,0 +1,439 @@
+//! Regex Sets
+//!
+//! Contains the definition for the `RegSet` struct. A set allows
+//! many patterns to be searched for in a single pass over a string,
+//! rather than searching with each `Regex` in turn.
+
+use std::os::raw::c_int;
+use std::ptr::null_mut;
+
+use super::{Captures, EncodedChars, Error, ErrorKind, Regex, Region, SearchOptions};
+
+/// Regex Set Search Lead
+///
+/// Controls how a `RegSet` is searched, and which match is reported
+/// when more than one of the patterns in the set matches.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RegSetLead {
+    /// Report the leftmost match. Each position in the string is
+    /// tried against every pattern before moving on to the next
+    /// position. This is usually faster for large sets.
+    Position,
+    /// Report the leftmost match. Each pattern is searched for across
+    /// the whole string in turn. This is usually faster for small sets
+    /// searched against long strings.
+    Regex,
+    /// Report a match for the first pattern in the set which matches
+    /// anywhere in the string, even if a later pattern matches
+    /// further to the left.
+    PriorityToRegexOrder,
+}
+
+impl RegSetLead {
+    fn as_raw(self) -> onig_sys::OnigRegSetLead {
+        match self {
+            RegSetLead::Position => onig_sys::OnigRegSetLead_ONIG_REGSET_POSITION_LEAD,
+            RegSetLead::Regex => onig_sys::OnigRegSetLead_ONIG_REGSET_REGEX_LEAD,
+            RegSetLead::PriorityToRegexOrder => {
+                onig_sys::OnigRegSetLead_ONIG_REGSET_PRIORITY_TO_REGEX_ORDER
+            }
+        }
+    }
+}
+
+/// A Set of Regular Expressions
+///
+/// Owns a list of compiled `Regex` values which can be searched for
+/// in a string at the same time. Each search reports the index of
+/// the pattern which matched, along with the position of the match.
+///
+/// All the patterns in a set must share the same encoding, and none
+/// of them can be compiled with `REGEX_OPTION_FIND_LONGEST`.
+///
+/// # Examples
+///
+/// ```
+/// use onig::{Regex, RegSet};
+///
+/// let mut set = RegSet::new(vec![
+///     Regex::new(r"\d+").unwrap(),
+///     Regex::new(r"[a-z]+").unwrap(),
+/// ]).unwrap();
+/// assert_eq!(set.find("  hello 42"), Some((1, 2, 7)));
+/// ```
+#[derive(Debug)]
+pub struct RegSet {
+    raw: *mut onig_sys::OnigRegSet,
+    regexes: Vec<Regex>,
+}
+
+unsafe impl Send for RegSet {}
+unsafe impl Sync for RegSet {}
+
+impl RegSet {
+    /// Create a Regex Set
+    ///
+    /// Builds a new set containing each of the given `regexes`. The
+    /// order of the patterns is preserved; the index reported by a
+    /// search is the index of the pattern in this list.
+    ///
+    /// # Arguments
+    ///
+    ///  * `regexes` - The compiled patterns to search for.
+    ///
+    /// # Returns
+    ///
+    /// The new set, or an `Error` if any of the patterns can't be
+    /// added to the set.
+    pub fn new<I>(regexes: I) -> Result<RegSet, Error>
+    where
+        I: IntoIterator<Item = Regex>,
+    {
+        let mut raw: *mut onig_sys::OnigRegSet = null_mut();
+        let r = unsafe { onig_sys::onig_regset_new(&mut raw, 0, null_mut()) };
+        if r != onig_sys::ONIG_NORMAL as i32 {
+            return Err(Error::from_code(r));
+        }
+
+        let mut set = RegSet {
+            raw,
+            regexes: Vec::new(),
+        };
+        for regex in regexes {
+            set.add(regex)?;
+        }
+        Ok(set)
+    }
+
+    /// Add a Regex to the Set
+    ///
+    /// Appends `regex` to the end of the set. The regex must have the
+    /// same encoding as the patterns already in the set.
+    pub fn add(&mut self, regex: Regex) -> Result<(), Error> {
+        if let Some(first) = self.regexes.first() {
+            if first.encoding() != regex.encoding() {
+                return Err(Error::custom(
+                    ErrorKind::InvalidEncoding,
+                    format!(
+                        "Regex encoding does not match set encoding ({0:?}, {1:?})",
+                        regex.encoding(),
+                        first.encoding()
+                    ),
+                ));
+            }
+        }
+        let r = unsafe { onig_sys::onig_regset_add(self.raw, regex.as_raw()) };
+        if r != onig_sys::ONIG_NORMAL as i32 {
+            return Err(Error::from_code(r));
+        }
+        self.regexes.push(regex);
+        Ok(())
+    }
+
+    /// Get the Number of Patterns in the Set
+    pub fn len(&self) -> usize {
+        self.regexes.len()
+    }
+
+    /// Is the Set Empty?
+    pub fn is_empty(&self) -> bool {
+        self.regexes.is_empty()
+    }
+
+    /// Get the Regex at the Given Index
+    pub fn get(&self, index: usize) -> Option<&Regex> {
+        self.regexes.get(index)
+    }
+
+    /// An Iterator Over the Patterns in the Set
+    pub fn iter(&self) -> std::slice::Iter<'_, Regex> {
+        self.regexes.iter()
+    }
+
+    /// Find the First Match in a String
+    ///
+    /// Searches `text` for any of the patterns in the set, using
+    /// `RegSetLead::Position`.
+    ///
+    /// # Returns
+    ///
+    /// `Some((index, start, end))` where `index` is the pattern in
+    /// the set which matched and `start` and `end` are the byte
+    /// offsets of the match. `None` if no pattern matches.
+    pub fn find(&mut self, text: &str) -> Option<(usize, usize, usize)> {
+        let mut region = Region::new();
+        self.search_with_options(
+            text,
+            0,
+            text.len(),
+            RegSetLead::Position,
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )
+        .and_then(|(index, _)| region.pos(0).map(|(beg, end)| (index, beg, end)))
+    }
+
+    /// Captures for the First Match in a String
+    ///
+    /// Searches `text` for any of the patterns in the set, using the
+    /// given `lead`. Returns the index of the pattern which matched
+    /// along with the capture groups for the match.
+    pub fn captures<'t>(
+        &mut self,
+        text: &'t str,
+        lead: RegSetLead,
+    ) -> Option<(usize, Captures<'t>)> {
+        let mut region = Region::new();
+        self.search_with_options(
+            text,
+            0,
+            text.len(),
+            lead,
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )
+        .map(|(index, pos)| {
+            (
+                index,
+                Captures::new(&self.regexes[index], text, region, pos),
+            )
+        })
+    }
+
+    /// Search for the Set in a String
+    ///
+    /// Searches for any of the patterns within the set in the given
+    /// string. This method works the same way as
+    /// `search_with_encoding`, but the encoding is always utf-8.
+    pub fn search_with_options(
+        &mut self,
+        str: &str,
+        from: usize,
+        to: usize,
+        lead: RegSetLead,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+    ) -> Option<(usize, usize)> {
+        self.search_with_encoding(str, from, to, lead, options, region)
+    }
+
+    /// Search for the Set in a String with an Encoding
+    ///
+    /// Searches for any of the patterns within the set in the given
+    /// buffer, between `from` and `to`. The encoding of the buffer
+    /// must match the encoding of the patterns in the set.
+    ///
+    /// # Arguments
+    ///
+    ///  * `chars` - The character buffer to search in.
+    ///  * `from` - The byte index in the passed slice to start search
+    ///  * `to` - The byte index in the passed slice to finish search
+    ///  * `lead` - Which match to report if several patterns match.
+    ///  * `options` - The options for the search.
+    ///  * `region` - The region for return group match range info
+    ///
+    /// # Returns
+    ///
+    /// `Some((index, pos))` if a pattern matches, where `index` is
+    /// the index of the pattern in the set and `pos` is the
+    /// byte-position of the start of the match. `None` if none of the
+    /// patterns match.
+    pub fn search_with_encoding<T>(
+        &mut self,
+        chars: T,
+        from: usize,
+        to: usize,
+        lead: RegSetLead,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+    ) -> Option<(usize, usize)>
+    where
+        T: EncodedChars,
+    {
+        let (beg, end) = (chars.start_ptr(), chars.limit_ptr());
+        if let Some(first) = self.regexes.first() {
+            if chars.encoding() != first.encoding() {
+                panic!(
+                    "Onig: RegSet search error: Set encoding does not match haystack encoding ({0:?}, {1:?})",
+                    chars.encoding(),
+                    first.encoding()
+                );
+            }
+        } else {
+            return None;
+        }
+        let mut match_pos: c_int = 0;
+        let r = unsafe {
+            let start = beg.add(from);
+            let range = beg.add(to);
+            if start > end {
+                panic!("Onig: RegSet search error: Start of match should be before end");
+            }
+            if range > end {
+                panic!("Onig: RegSet search error: Limit of match should be before end");
+            }
+            onig_sys::onig_regset_search(
+                self.raw,
+                beg,
+                end,
+                start,
+                range,
+                lead.as_raw(),
+                options.bits(),
+                &mut match_pos,
+            )
+        };
+        crate::callout::resume_callout_panic();
+
+        if r >= 0 {
+            if let Some(region) = region {
+                unsafe {
+                    let raw_region = onig_sys::onig_regset_get_region(self.raw, r);
+                    onig_sys::onig_region_copy(&mut region.raw, raw_region);
+                }
+            }
+            Some((r as usize, match_pos as usize))
+        } else if r == onig_sys::ONIG_MISMATCH {
+            None
+        } else {
+            panic!(
+                "Onig: RegSet search error: {}",
+                Error::from_code(r).description()
+            )
+        }
+    }
+}
+
+impl Drop for RegSet {
+    fn drop(&mut self) {
+        unsafe {
+            // Detach each regex from the set before freeing it. The
+            // regexes are owned by `self.regexes` and are freed when
+            // that is dropped.
+            for index in (0..self.regexes.len()).rev() {
+                onig_sys::onig_regset_replace(self.raw, index as c_int, null_mut());
+            }
+            onig_sys::onig_regset_free(self.raw);
+        }
+    }
+}
+
+impl<'a> IntoIterator for &'a RegSet {
+    type Item = &'a Regex;
+    type IntoIter = std::slice::Iter<'a, Regex>;
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    fn words_and_numbers() -> RegSet {
+        RegSet::new(vec![
+            Regex::new(r"\d+").unwrap(),
+            Regex::new(r"[a-z]+").unwrap(),
+            Regex::new(r"[a-z]+\d").unwrap(),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_regset_create() {
+        let set = words_and_numbers();
+        assert_eq!(set.len(), 3);
+        assert!(!set.is_empty());
+        assert!(set.get(1).unwrap().is_match("abc"));
+        assert!(set.get(3).is_none());
+        assert_eq!(set.iter().count(), 3);
+    }
+
+    #[test]
+    fn test_regset_empty() {
+        let mut set = RegSet::new(vec![]).unwrap();
+        assert!(set.is_empty());
+        assert_eq!(set.find("hello"), None);
+    }
+
+    #[test]
+    fn test_regset_find_position_lead() {
+        let mut set = words_and_numbers();
+        assert_eq!(set.find("  hello 42"), Some((1, 2, 7)));
+        assert_eq!(set.find("-- 42 hello"), Some((0, 3, 5)));
+        assert_eq!(set.find("--"), None);
+    }
+
+    #[test]
+    fn test_regset_search_regex_lead() {
+        let mut set = words_and_numbers();
+        let mut region = Region::new();
+        let text = "-- 42 hello";
+        let r = set.search_with_options(
+            text,
+            0,
+            text.len(),
+            RegSetLead::Regex,
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        );
+        assert_eq!(r, Some((0, 3)));
+        assert_eq!(region.pos(0), Some((3, 5)));
+        let r = set.search_with_options(
+            text,
+            5,
+            text.len(),
+            RegSetLead::Regex,
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        );
+        assert_eq!(r, Some((1, 6)));
+        assert_eq!(region.pos(0), Some((6, 11)));
+    }
+
+    #[test]
+    fn test_regset_priority_to_regex_order() {
+        let mut set = words_and_numbers();
+        let (index, caps) = set
+            .captures("hello 1", RegSetLead::PriorityToRegexOrder)
+            .unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(caps.at(0), Some("1"));
+        let (index, caps) = set.captures("hello 1", RegSetLead::Position).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(caps.at(0), Some("hello"));
+    }
+
+    #[test]
+    fn test_regset_captures() {
+        let mut set = RegSet::new(vec![
+            Regex::new(r"(\d+)-(\d+)").unwrap(),
+            Regex::new(r"(\w+)@(\w+)").unwrap(),
+        ])
+        .unwrap();
+        let (index, caps) = set.captures("mail: me@host", RegSetLead::Position).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(caps.offset(), 6);
+        assert_eq!(caps.at(1), Some("me"));
+        assert_eq!(caps.at(2), Some("host"));
+    }
+
+    #[test]
+    fn test_regset_rejects_find_longest() {
+        let regex = Regex::with_options(
+            "a+",
+            RegexOptions::REGEX_OPTION_FIND_LONGEST,
+            Syntax::default(),
+        )
+        .unwrap();
+        assert!(RegSet::new(vec![regex]).is_err());
+    }
+
+    #[test]
+    fn test_regset_rejects_mixed_encodings() {
+        let mut set = RegSet::new(vec![Regex::new("a").unwrap()]).unwrap();
+        let ascii = Regex::with_encoding(EncodedBytes::ascii(b"b")).unwrap();
+        assert!(set.add(ascii).is_err());
+        assert_eq!(set.len(), 1);
+    }
+}
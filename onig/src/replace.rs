@@ -1,3 +1,4 @@
+use super::find::Haystack;
 use super::{Captures, Regex};
 use std::borrow::Cow;
 
@@ -8,10 +9,14 @@ use std::borrow::Cow;
 /// needs a user-supplied `Replacer` implemenation can be
 /// provided. For an example of a custom replacer implementation check
 /// out `examples/dollar.rs` in the Onig crate.
-pub trait Replacer {
+///
+/// `T` is the type of the text being replaced in. It is `str` for
+/// `onig::Regex` and `[u8]` for `onig::bytes::Regex`, where byte
+/// slice literals and callbacks returning `Vec<u8>` can be used.
+pub trait Replacer<T: ?Sized + ToOwned = str> {
     /// Returns a possibly owned string that is used to replace the match
     /// corresponding to the `caps` capture group.
-    fn reg_replace(&mut self, caps: &Captures) -> Cow<str>;
+    fn reg_replace(&mut self, caps: &Captures<T>) -> Cow<T>;
 }
 
 /// Replacement using Literal Strings
@@ -31,6 +36,23 @@ where
     }
 }
 
+/// Replacement using Literal Byte Slices
+impl Replacer<[u8]> for &[u8] {
+    fn reg_replace(&mut self, _: &Captures<[u8]>) -> Cow<'_, [u8]> {
+        (*self).into()
+    }
+}
+
+/// Replacement using `FnMut` Callbacks Returning Bytes
+impl<F> Replacer<[u8]> for F
+where
+    F: FnMut(&Captures<[u8]>) -> Vec<u8>,
+{
+    fn reg_replace<'a>(&'a mut self, caps: &Captures<[u8]>) -> Cow<'a, [u8]> {
+        (*self)(caps).into()
+    }
+}
+
 impl Regex {
     /// Replaces the leftmost-first match with the replacement provided.
     /// The replacement can be a regular string or a function that takes
@@ -86,20 +108,28 @@ impl Regex {
     ///
     /// See the documentation for `replace` for details on how to access
     /// submatches in the replacement string.
-    pub fn replacen<R: Replacer>(&self, text: &str, limit: usize, mut rep: R) -> String {
-        let mut new = String::with_capacity(text.len());
+    pub fn replacen<R: Replacer>(&self, text: &str, limit: usize, rep: R) -> String {
+        self.replacen_in(text, limit, rep)
+    }
+
+    pub(crate) fn replacen_in<T, R>(&self, text: &T, limit: usize, mut rep: R) -> T::Owned
+    where
+        T: Haystack + ?Sized,
+        R: Replacer<T>,
+    {
+        let mut new = T::with_capacity(text.len());
         let mut last_match = 0;
-        for (i, cap) in self.captures_iter(text).enumerate() {
+        for (i, cap) in self.captures_iter_in(text).enumerate() {
             if limit > 0 && i >= limit {
                 break;
             }
             // unwrap on 0 is OK because captures only reports matches
             let (s, e) = cap.pos(0).unwrap();
-            new.push_str(&text[last_match..s]);
-            new.push_str(&rep.reg_replace(&cap));
+            text.slice(last_match, s).push_to(&mut new);
+            rep.reg_replace(&cap).push_to(&mut new);
             last_match = e;
         }
-        new.push_str(&text[last_match..]);
+        text.slice(last_match, text.len()).push_to(&mut new);
         new
     }
 }
@@ -0,0 +1,137 @@
+//! Text Segmentation
+//!
+//! Splits strings into the text segments Oniguruma uses for `\X` and
+//! `\y`: extended grapheme clusters and words, as defined by
+//! [Unicode Standard Annex #29](https://www.unicode.org/reports/tr29/).
+//! Each iterator is built on a compiled `\X` regex, so the segments
+//! always agree with what the regex engine considers a single
+//! character or word. `graphemes` gives the same result as Ruby's
+//! `String#grapheme_clusters`.
+//!
+//! # Examples
+//!
+//! ```
+//! use onig::segment;
+//!
+//! let graphemes: Vec<&str> = segment::graphemes("e\u{301}\r\n🇬🇧").collect();
+//! assert_eq!(graphemes, vec!["e\u{301}", "\r\n", "🇬🇧"]);
+//!
+//! let words: Vec<&str> = segment::words("can't stop").collect();
+//! assert_eq!(words, vec!["can't", " ", "stop"]);
+//! ```
+
+use std::iter::FusedIterator;
+
+use once_cell::sync::Lazy;
+
+use super::{FindMatches, Regex, RegexOptions, Syntax};
+
+static GRAPHEME: Lazy<Regex> =
+    Lazy::new(|| segment_regex(RegexOptions::REGEX_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER));
+
+static WORD: Lazy<Regex> =
+    Lazy::new(|| segment_regex(RegexOptions::REGEX_OPTION_TEXT_SEGMENT_WORD));
+
+/// Compile `\X` with the given text segment mode. The syntax is given
+/// explicitly so that changes to the default syntax don't affect it.
+fn segment_regex(mode: RegexOptions) -> Regex {
+    Regex::with_options(r"\X", mode, Syntax::oniguruma()).expect("\\X is a valid pattern")
+}
+
+/// Iterate Over Grapheme Clusters
+///
+/// Returns an iterator over the extended grapheme clusters of `text`,
+/// the user-perceived characters which `\X` matches.
+pub fn graphemes(text: &str) -> Graphemes<'_> {
+    Graphemes {
+        text,
+        matches: GRAPHEME.find_iter(text),
+    }
+}
+
+/// Iterate Over Words
+///
+/// Returns an iterator over the word segments of `text`. Every part
+/// of the string is part of a segment, so the spaces and punctuation
+/// between words are returned as segments of their own.
+pub fn words(text: &str) -> Words<'_> {
+    Words {
+        text,
+        matches: WORD.find_iter(text),
+    }
+}
+
+/// Grapheme Cluster Iterator
+///
+/// Yields the extended grapheme clusters of a string. Created by
+/// `segment::graphemes`.
+pub struct Graphemes<'t> {
+    text: &'t str,
+    matches: FindMatches<'static, 't>,
+}
+
+impl<'t> Iterator for Graphemes<'t> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        self.matches.next().map(|(beg, end)| &self.text[beg..end])
+    }
+}
+
+impl<'t> FusedIterator for Graphemes<'t> {}
+
+/// Word Iterator
+///
+/// Yields the word segments of a string. Created by
+/// `segment::words`.
+pub struct Words<'t> {
+    text: &'t str,
+    matches: FindMatches<'static, 't>,
+}
+
+impl<'t> Iterator for Words<'t> {
+    type Item = &'t str;
+
+    fn next(&mut self) -> Option<&'t str> {
+        self.matches.next().map(|(beg, end)| &self.text[beg..end])
+    }
+}
+
+impl<'t> FusedIterator for Words<'t> {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+
+    #[test]
+    fn test_graphemes() {
+        let text = "a\u{308}o\u{301}\r\n👨\u{200d}👩\u{200d}👧🇯🇵\u{1100}\u{1161}\u{11a8}";
+        let graphemes: Vec<&str> = segment::graphemes(text).collect();
+        assert_eq!(
+            graphemes,
+            vec![
+                "a\u{308}",
+                "o\u{301}",
+                "\r\n",
+                "👨\u{200d}👩\u{200d}👧",
+                "🇯🇵",
+                "\u{1100}\u{1161}\u{11a8}"
+            ]
+        );
+        assert_eq!(segment::graphemes("").next(), None);
+    }
+
+    #[test]
+    fn test_words() {
+        let text = "The quick (\"brown\") fox can't jump 32.3 feet, right?";
+        let words: Vec<&str> = segment::words(text).collect();
+        assert_eq!(
+            words,
+            vec![
+                "The", " ", "quick", " ", "(", "\"", "brown", "\"", ")", " ", "fox", " ", "can't",
+                " ", "jump", " ", "32.3", " ", "feet", ",", " ", "right", "?"
+            ]
+        );
+        assert_eq!(words.concat(), text);
+    }
+}
@@ -0,0 +1,469 @@
+//! Serde Support
+//!
+//! Implements `Serialize` and `Deserialize` for `Regex` and the option
+//! flag types when the `serde` feature is enabled.
+//!
+//! Flags are represented as a list of the names of the flags which are
+//! set, such as `["REGEX_OPTION_IGNORECASE", "REGEX_OPTION_EXTEND"]`.
+//! A `Regex` is represented by its pattern, options, syntax name,
+//! encoding name and case fold flags, and is recompiled when it is
+//! deserialized.
+//!
+//! The pattern is always stored as text. Patterns in UTF-16, UTF-32 and
+//! ISO-8859-1 are transcoded, but patterns in other encodings can only
+//! be serialized if they are ASCII.
+
+use std::borrow::Cow;
+use std::convert::TryFrom;
+use std::fmt;
+
+use serde::de::{self, Deserializer, SeqAccess, Visitor};
+use serde::ser::{self, SerializeSeq, Serializer};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    CaseFold, EncodedBytes, Encoding, Regex, RegexBuilder, RegexOptions, SearchOptions, Syntax,
+    SyntaxBehavior, SyntaxOperator,
+};
+
+/// Implement `Serialize` and `Deserialize` for a flags type as a list
+/// of the names of the given flags.
+macro_rules! impl_serde_flags {
+    ($flags:ident { $($flag:ident,)* }) => {
+        impl $flags {
+            const NAMES: &'static [(&'static str, $flags)] = &[
+                $((stringify!($flag), $flags::$flag),)*
+            ];
+        }
+
+        impl Serialize for $flags {
+            fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                let mut remaining = *self;
+                let mut seq = serializer.serialize_seq(None)?;
+                for &(name, flag) in $flags::NAMES {
+                    if !flag.is_empty() && remaining.contains(flag) {
+                        seq.serialize_element(name)?;
+                        remaining.remove(flag);
+                    }
+                }
+                seq.end()
+            }
+        }
+
+        impl<'de> Deserialize<'de> for $flags {
+            fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                struct FlagsVisitor;
+
+                impl<'de> Visitor<'de> for FlagsVisitor {
+                    type Value = $flags;
+
+                    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                        write!(f, "a list of {} flag names", stringify!($flags))
+                    }
+
+                    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<$flags, A::Error> {
+                        let mut flags = $flags::empty();
+                        while let Some(name) = seq.next_element::<Cow<str>>()? {
+                            let flag = $flags::NAMES
+                                .iter()
+                                .find(|(known, _)| *known == name)
+                                .ok_or_else(|| {
+                                    de::Error::custom(format!(
+                                        "unknown {} flag `{}`",
+                                        stringify!($flags),
+                                        name
+                                    ))
+                                })?;
+                            flags.insert(flag.1);
+                        }
+                        Ok(flags)
+                    }
+                }
+
+                deserializer.deserialize_seq(FlagsVisitor)
+            }
+        }
+    };
+}
+
+impl_serde_flags!(RegexOptions {
+    REGEX_OPTION_NONE,
+    REGEX_OPTION_IGNORECASE,
+    REGEX_OPTION_EXTEND,
+    REGEX_OPTION_MULTILINE,
+    REGEX_OPTION_SINGLELINE,
+    REGEX_OPTION_FIND_LONGEST,
+    REGEX_OPTION_FIND_NOT_EMPTY,
+    REGEX_OPTION_NEGATE_SINGLELINE,
+    REGEX_OPTION_DONT_CAPTURE_GROUP,
+    REGEX_OPTION_CAPTURE_GROUP,
+    REGEX_OPTION_IGNORECASE_IS_ASCII,
+    REGEX_OPTION_WORD_IS_ASCII,
+    REGEX_OPTION_DIGIT_IS_ASCII,
+    REGEX_OPTION_SPACE_IS_ASCII,
+    REGEX_OPTION_POSIX_IS_ASCII,
+    REGEX_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER,
+    REGEX_OPTION_TEXT_SEGMENT_WORD,
+});
+
+impl_serde_flags!(SearchOptions {
+    SEARCH_OPTION_NONE,
+    SEARCH_OPTION_NOTBOL,
+    SEARCH_OPTION_NOTEOL,
+    SEARCH_OPTION_WHOLE_STRING,
+    SEARCH_OPTION_FIND_NOT_EMPTY,
+    SEARCH_OPTION_NOT_BEGIN_STRING,
+    SEARCH_OPTION_NOT_END_STRING,
+    SEARCH_OPTION_NOT_BEGIN_POSITION,
+    SEARCH_OPTION_CALLBACK_EACH_MATCH,
+    SEARCH_OPTION_CHECK_VALIDITY_OF_STRING,
+});
+
+impl_serde_flags!(CaseFold {
+    CASE_FOLD_ASCII_ONLY,
+    CASE_FOLD_MULTI_CHAR,
+    CASE_FOLD_TURKISH_AZERI,
+});
+
+impl_serde_flags!(SyntaxOperator {
+    SYNTAX_OPERATOR_DOT_ANYCHAR,
+    SYNTAX_OPERATOR_ASTERISK_ZERO_INF,
+    SYNTAX_OPERATOR_PLUS_ONE_INF,
+    SYNTAX_OPERATOR_QMARK_ZERO_ONE,
+    SYNTAX_OPERATOR_QMARK_CAPITAL_P_NAME,
+    SYNTAX_OPERATOR_BRACE_INTERVAL,
+    SYNTAX_OPERATOR_ESC_BRACE_INTERVAL,
+    SYNTAX_OPERATOR_VBAR_ALT,
+    SYNTAX_OPERATOR_ESC_VBAR_ALT,
+    SYNTAX_OPERATOR_LPAREN_SUBEXP,
+    SYNTAX_OPERATOR_ESC_LPAREN_SUBEXP,
+    SYNTAX_OPERATOR_ESC_AZ_BUF_ANCHOR,
+    SYNTAX_OPERATOR_ESC_CAPITAL_G_BEGIN_ANCHOR,
+    SYNTAX_OPERATOR_DECIMAL_BACKREF,
+    SYNTAX_OPERATOR_BRACKET_CC,
+    SYNTAX_OPERATOR_ESC_W_WORD,
+    SYNTAX_OPERATOR_ESC_LTGT_WORD_BEGIN_END,
+    SYNTAX_OPERATOR_ESC_B_WORD_BOUND,
+    SYNTAX_OPERATOR_ESC_S_WHITE_SPACE,
+    SYNTAX_OPERATOR_ESC_D_DIGIT,
+    SYNTAX_OPERATOR_LINE_ANCHOR,
+    SYNTAX_OPERATOR_POSIX_BRACKET,
+    SYNTAX_OPERATOR_QMARK_NON_GREEDY,
+    SYNTAX_OPERATOR_ESC_CONTROL_CHARS,
+    SYNTAX_OPERATOR_ESC_C_CONTROL,
+    SYNTAX_OPERATOR_ESC_OCTAL3,
+    SYNTAX_OPERATOR_ESC_X_HEX2,
+    SYNTAX_OPERATOR_ESC_X_BRACE_HEX8,
+    SYNTAX_OPERATOR_VARIABLE_META_CHARACTERS,
+    SYNTAX_OPERATOR_ESC_CAPITAL_Q_QUOTE,
+    SYNTAX_OPERATOR_QMARK_GROUP_EFFECT,
+    SYNTAX_OPERATOR_OPTION_PERL,
+    SYNTAX_OPERATOR_OPTION_RUBY,
+    SYNTAX_OPERATOR_PLUS_POSSESSIVE_REPEAT,
+    SYNTAX_OPERATOR_PLUS_POSSESSIVE_INTERVAL,
+    SYNTAX_OPERATOR_CCLASS_SET_OP,
+    SYNTAX_OPERATOR_QMARK_LT_NAMED_GROUP,
+    SYNTAX_OPERATOR_ESC_K_NAMED_BACKREF,
+    SYNTAX_OPERATOR_ESC_G_SUBEXP_CALL,
+    SYNTAX_OPERATOR_ATMARK_CAPTURE_HISTORY,
+    SYNTAX_OPERATOR_ESC_CAPITAL_C_BAR_CONTROL,
+    SYNTAX_OPERATOR_ESC_CAPITAL_M_BAR_META,
+    SYNTAX_OPERATOR_ESC_V_VTAB,
+    SYNTAX_OPERATOR_ESC_U_HEX4,
+    SYNTAX_OPERATOR_ESC_GNU_BUF_ANCHOR,
+    SYNTAX_OPERATOR_ESC_P_BRACE_CHAR_PROPERTY,
+    SYNTAX_OPERATOR_ESC_P_BRACE_CIRCUMFLEX_NOT,
+    SYNTAX_OPERATOR_ESC_H_XDIGIT,
+    SYNTAX_OPERATOR_INEFFECTIVE_ESCAPE,
+    SYNTAX_OPERATOR_QMARK_BRACE_CALLOUT_CONTENTS,
+    SYNTAX_OPERATOR_ASTERISK_CALLOUT_NAME,
+});
+
+impl_serde_flags!(SyntaxBehavior {
+    SYNTAX_BEHAVIOR_CONTEXT_INDEP_REPEAT_OPS,
+    SYNTAX_BEHAVIOR_CONTEXT_INVALID_REPEAT_OPS,
+    SYNTAX_BEHAVIOR_ALLOW_UNMATCHED_CLOSE_SUBEXP,
+    SYNTAX_BEHAVIOR_ALLOW_INVALID_INTERVAL,
+    SYNTAX_BEHAVIOR_ALLOW_INTERVAL_LOW_ABBREV,
+    SYNTAX_BEHAVIOR_STRICT_CHECK_BACKREF,
+    SYNTAX_BEHAVIOR_DIFFERENT_LEN_ALT_LOOK_BEHIND,
+    SYNTAX_BEHAVIOR_CAPTURE_ONLY_NAMED_GROUP,
+    SYNTAX_BEHAVIOR_ALLOW_MULTIPLEX_DEFINITION_NAME,
+    SYNTAX_BEHAVIOR_FIXED_INTERVAL_IS_GREEDY_ONLY,
+    SYNTAX_BEHAVIOR_NOT_NEWLINE_IN_NEGATIVE_CC,
+    SYNTAX_BEHAVIOR_BACKSLASH_ESCAPE_IN_CC,
+    SYNTAX_BEHAVIOR_ALLOW_DOUBLE_RANGE_OP_IN_CC,
+    SYNTAX_BEHAVIOR_WARN_CC_OP_NOT_ESCAPED,
+    SYNTAX_BEHAVIOR_WARN_REDUNDANT_NESTED_REPEAT,
+});
+
+/// The serialized form of a `Regex`.
+///
+/// Everything but the pattern is optional when deserializing, so a
+/// regex can be written as just `{ "pattern": "..." }`.
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "Regex")]
+struct SerializedRegex<'a> {
+    pattern: Cow<'a, str>,
+    #[serde(default = "RegexOptions::empty")]
+    options: RegexOptions,
+    #[serde(default = "default_syntax")]
+    syntax: Cow<'a, str>,
+    #[serde(default = "default_encoding")]
+    encoding: Cow<'a, str>,
+    #[serde(default)]
+    case_fold: Option<CaseFold>,
+}
+
+fn default_syntax() -> Cow<'static, str> {
+    Cow::Borrowed("default")
+}
+
+fn default_encoding() -> Cow<'static, str> {
+    Cow::Borrowed(Encoding::Utf8.name())
+}
+
+/// Encode `pattern` in `encoding`, or fail if it can't be represented
+/// without a transcoder.
+fn encode_pattern(pattern: &str, encoding: Encoding) -> Result<Cow<'_, [u8]>, String> {
+    let mut bytes = Vec::new();
+    match encoding {
+        Encoding::Utf8 => return Ok(Cow::Borrowed(pattern.as_bytes())),
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            for unit in pattern.encode_utf16() {
+                bytes.extend_from_slice(&match encoding {
+                    Encoding::Utf16Le => unit.to_le_bytes(),
+                    _ => unit.to_be_bytes(),
+                });
+            }
+        }
+        Encoding::Utf32Le | Encoding::Utf32Be => {
+            for c in pattern.chars() {
+                bytes.extend_from_slice(&match encoding {
+                    Encoding::Utf32Le => u32::from(c).to_le_bytes(),
+                    _ => u32::from(c).to_be_bytes(),
+                });
+            }
+        }
+        Encoding::Iso8859_1 => {
+            for c in pattern.chars() {
+                bytes.push(
+                    u8::try_from(u32::from(c))
+                        .map_err(|_| format!("regex pattern can't be encoded as {}", encoding))?,
+                );
+            }
+        }
+        _ if pattern.is_ascii() => return Ok(Cow::Borrowed(pattern.as_bytes())),
+        _ => {
+            return Err(format!(
+                "regex pattern must be ASCII to be encoded as {}",
+                encoding
+            ))
+        }
+    }
+    Ok(Cow::Owned(bytes))
+}
+
+impl Serialize for Regex {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let syntax = self
+            .syntax()
+            .name()
+            .ok_or_else(|| ser::Error::custom("regex uses a custom syntax"))?;
+        let encoding = Encoding::from_raw(self.encoding())
+            .ok_or_else(|| ser::Error::custom("regex uses a custom encoding"))?;
+        if !encoding.is_unicode() && !self.raw.pattern.is_ascii() {
+            return Err(ser::Error::custom(format!(
+                "regex pattern must be ASCII to be encoded as {}",
+                encoding
+            )));
+        }
+        // Only keep the decoded pattern if it encodes back to exactly
+        // the pattern the regex was compiled from.
+        let pattern = match self.as_str() {
+            Some(pattern) => Cow::Borrowed(pattern),
+            None => Cow::Owned(self.to_string()),
+        };
+        if encode_pattern(&pattern, encoding).map_err(ser::Error::custom)? != &self.raw.pattern[..]
+        {
+            return Err(ser::Error::custom(format!(
+                "regex pattern is not valid {}",
+                encoding
+            )));
+        }
+        SerializedRegex {
+            pattern,
+            options: self.options(),
+            syntax: Cow::Borrowed(syntax),
+            encoding: Cow::Borrowed(encoding.name()),
+            case_fold: Some(self.case_fold_flag()),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Regex {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let serialized = SerializedRegex::deserialize(deserializer)?;
+        let syntax = Syntax::from_name(&serialized.syntax)
+            .ok_or_else(|| de::Error::custom(format!("unknown syntax `{}`", serialized.syntax)))?;
+        let encoding: Encoding = serialized.encoding.parse().map_err(de::Error::custom)?;
+        let pattern = encode_pattern(&serialized.pattern, encoding).map_err(de::Error::custom)?;
+        let mut builder = RegexBuilder::with_encoding(EncodedBytes::new(&pattern, encoding));
+        builder.options(serialized.options).syntax(syntax);
+        if let Some(case_fold) = serialized.case_fold {
+            builder.case_fold_flag(case_fold);
+        }
+        builder.build().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_serialize_flags() {
+        let options = RegexOptions::REGEX_OPTION_IGNORECASE | RegexOptions::REGEX_OPTION_EXTEND;
+        let value = serde_json::to_value(options).unwrap();
+        assert_eq!(
+            value,
+            json!(["REGEX_OPTION_IGNORECASE", "REGEX_OPTION_EXTEND"])
+        );
+        assert_eq!(
+            serde_json::from_value::<RegexOptions>(value).unwrap(),
+            options
+        );
+
+        let none = serde_json::to_value(SearchOptions::SEARCH_OPTION_NONE).unwrap();
+        assert_eq!(none, json!([]));
+        assert_eq!(
+            serde_json::from_value::<SearchOptions>(json!(["SEARCH_OPTION_NONE"])).unwrap(),
+            SearchOptions::SEARCH_OPTION_NONE
+        );
+
+        let operators = Syntax::perl_ng().operators();
+        let value = serde_json::to_value(operators).unwrap();
+        assert_eq!(
+            serde_json::from_value::<SyntaxOperator>(value).unwrap(),
+            operators
+        );
+
+        let behavior = Syntax::ruby().behavior();
+        let value = serde_json::to_value(behavior).unwrap();
+        assert_eq!(
+            serde_json::from_value::<SyntaxBehavior>(value).unwrap(),
+            behavior
+        );
+    }
+
+    #[test]
+    fn test_deserialize_unknown_flag() {
+        let err = serde_json::from_value::<RegexOptions>(json!(["REGEX_OPTION_BOGUS"]))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown RegexOptions flag `REGEX_OPTION_BOGUS`"));
+    }
+
+    #[test]
+    fn test_regex_round_trip() {
+        let regex = RegexBuilder::new(r"(?<word>\w+)\s+")
+            .options(RegexOptions::REGEX_OPTION_IGNORECASE)
+            .syntax(Syntax::perl_ng())
+            .case_fold_flag(CaseFold::CASE_FOLD_ASCII_ONLY)
+            .build()
+            .unwrap();
+        let value = serde_json::to_value(&regex).unwrap();
+        assert_eq!(
+            value,
+            json!({
+                "pattern": r"(?<word>\w+)\s+",
+                "options": ["REGEX_OPTION_IGNORECASE", "REGEX_OPTION_SINGLELINE"],
+                "syntax": "perl_ng",
+                "encoding": "UTF-8",
+                "case_fold": ["CASE_FOLD_ASCII_ONLY"],
+            })
+        );
+
+        let copy: Regex = serde_json::from_value(value).unwrap();
+        assert_eq!(copy.as_str(), regex.as_str());
+        assert_eq!(copy.options(), regex.options());
+        assert_eq!(copy.syntax().name(), Some("perl_ng"));
+        assert_eq!(copy.case_fold_flag(), CaseFold::CASE_FOLD_ASCII_ONLY);
+        assert_eq!(
+            copy.captures("HELLO world").unwrap().name("word"),
+            Some("HELLO")
+        );
+    }
+
+    #[test]
+    fn test_deserialize_regex_defaults() {
+        let regex: Regex = serde_json::from_str(r#"{ "pattern": "a+b" }"#).unwrap();
+        assert_eq!(regex.options(), RegexOptions::REGEX_OPTION_NONE);
+        assert_eq!(regex.syntax().name(), Syntax::default().name());
+        assert_eq!(Encoding::from_raw(regex.encoding()), Some(Encoding::Utf8));
+        assert_eq!(regex.case_fold_flag(), defaults::case_fold_flag());
+        assert!(regex.is_match("aaab"));
+    }
+
+    #[test]
+    fn test_deserialize_regex_errors() {
+        let err = serde_json::from_str::<Regex>(r#"{ "pattern": "a(" }"#).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("end pattern with unmatched parenthesis"));
+
+        let err = serde_json::from_str::<Regex>(r#"{ "pattern": "a", "syntax": "klingon" }"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("unknown syntax `klingon`"));
+
+        let err = serde_json::from_str::<Regex>(r#"{ "pattern": "a", "encoding": "EBCDIC" }"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown encoding name 'EBCDIC'"));
+    }
+
+    #[test]
+    fn test_regex_round_trip_encodings() {
+        for &(pattern, encoding) in &[
+            (&b"\xe9+"[..], Encoding::Iso8859_1),
+            (&b"\xe9\0+\0"[..], Encoding::Utf16Le),
+            (&b"\0\0\0\xe9\0\0\0+"[..], Encoding::Utf32Be),
+            (&b"a+"[..], Encoding::ShiftJis),
+        ] {
+            let regex = Regex::with_encoding(EncodedBytes::new(pattern, encoding)).unwrap();
+            let value = serde_json::to_value(&regex).unwrap();
+            assert_eq!(value["encoding"], json!(encoding.name()));
+            let copy: Regex = serde_json::from_value(value).unwrap();
+            assert_eq!(copy.raw.pattern, regex.raw.pattern);
+            assert_eq!(copy.encoding(), regex.encoding());
+        }
+    }
+
+    #[test]
+    fn test_regex_non_ascii_without_transcoding() {
+        let regex =
+            Regex::with_encoding(EncodedBytes::new(b"\x83\x41+", Encoding::ShiftJis)).unwrap();
+        let err = serde_json::to_value(&regex).unwrap_err().to_string();
+        assert!(err.contains("must be ASCII"));
+
+        let err = serde_json::from_str::<Regex>(r#"{ "pattern": "é", "encoding": "Shift_JIS" }"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("must be ASCII"));
+
+        let err = serde_json::from_str::<Regex>(r#"{ "pattern": "Ā", "encoding": "ISO-8859-1" }"#)
+            .unwrap_err();
+        assert!(err.to_string().contains("can't be encoded as ISO-8859-1"));
+
+        let regex = Regex::with_encoding(EncodedBytes::new(b"\xe9", Encoding::Ascii)).unwrap();
+        assert!(serde_json::to_value(&regex).is_err());
+    }
+
+    #[test]
+    fn test_serialize_custom_syntax() {
+        let mut syntax = *Syntax::ruby();
+        syntax.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_DOT_ANYCHAR);
+        let regex = RegexBuilder::new("a").syntax(&syntax).build().unwrap();
+        assert!(serde_json::to_value(&regex).is_err());
+    }
+}
@@ -17,6 +17,24 @@ pub enum MetaChar {
     Ineffective,
 }
 
+type SyntaxConstructor = fn() -> &'static Syntax;
+
+/// The built in syntaxes, named after their constructors.
+const BUILTIN_SYNTAXES: &[(&str, SyntaxConstructor)] = &[
+    ("asis", Syntax::asis),
+    ("emacs", Syntax::emacs),
+    ("gnu_regex", Syntax::gnu_regex),
+    ("grep", Syntax::grep),
+    ("java", Syntax::java),
+    ("oniguruma", Syntax::oniguruma),
+    ("perl", Syntax::perl),
+    ("perl_ng", Syntax::perl_ng),
+    ("posix_basic", Syntax::posix_basic),
+    ("posix_extended", Syntax::posix_extended),
+    ("python", Syntax::python),
+    ("ruby", Syntax::ruby),
+];
+
 /// Onig Syntax Wrapper
 ///
 /// Each syntax dfines a flavour of regex syntax. This type allows
@@ -92,11 +110,39 @@ impl Syntax {
         unsafe { transmute(&onig_sys::OnigSyntaxOniguruma) }
     }
 
-    /// Default syntax (Ruby syntax)
+    /// Default syntax
+    ///
+    /// The syntax used when no other is given. It can be changed with
+    /// `onig::defaults::set_syntax`.
     pub fn default() -> &'static Syntax {
         unsafe { transmute(onig_sys::OnigDefaultSyntax) }
     }
 
+    /// Find a Built In Syntax by Name
+    ///
+    /// Looks up one of the built in syntaxes by the name of its
+    /// constructor, such as `"perl_ng"` or `"default"`.
+    pub fn from_name(name: &str) -> Option<&'static Syntax> {
+        if name == "default" {
+            return Some(Syntax::default());
+        }
+        BUILTIN_SYNTAXES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, syntax)| syntax())
+    }
+
+    /// The Name of a Built In Syntax
+    ///
+    /// Returns the name of the built in syntax with the same settings
+    /// as this one, or `None` if this is a custom syntax.
+    pub fn name(&self) -> Option<&'static str> {
+        BUILTIN_SYNTAXES
+            .iter()
+            .find(|(_, syntax)| syntax().raw == self.raw)
+            .map(|(name, _)| *name)
+    }
+
     /// Retrieve the operators for this syntax
     pub fn operators(&self) -> SyntaxOperator {
         SyntaxOperator::from_bits_truncate(self.operators_bits())
@@ -219,4 +265,18 @@ mod test {
         syn.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_ESC_X_BRACE_HEX8);
         assert_eq!(Syntax::python().raw, syn.raw);
     }
+
+    #[test]
+    fn builtin_names() {
+        for &(name, syntax) in BUILTIN_SYNTAXES {
+            assert_eq!(syntax().name(), Some(name));
+            assert!(std::ptr::eq(Syntax::from_name(name).unwrap(), syntax()));
+        }
+        assert!(Syntax::default().name().is_some());
+        assert!(Syntax::from_name("klingon").is_none());
+
+        let mut custom = *Syntax::ruby();
+        custom.disable_operators(SyntaxOperator::SYNTAX_OPERATOR_DOT_ANYCHAR);
+        assert_eq!(custom.name(), None);
+    }
 }
@@ -0,0 +1,510 @@
+//! UTF-16 Regexes
+//!
+//! This module contains a `Regex` type which searches native-endian
+//! UTF-16 buffers, as used by JavaScript, Windows and the Language
+//! Server Protocol, without transcoding them to UTF-8 first. All
+//! positions given to and returned from this API are in UTF-16 code
+//! units rather than bytes.
+//!
+//! Buffers are checked before they are searched. Oniguruma assumes
+//! its input is well formed, so the search methods panic if given a
+//! buffer containing an unpaired surrogate.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use onig::utf16::Regex;
+//!
+//! let text: Vec<u16> = "héllo wörld".encode_utf16().collect();
+//! let regex = Regex::new(r"w(\w+)").unwrap();
+//! let caps = regex.captures(&text).unwrap();
+//! assert_eq!(caps.pos(0), Some((6, 11)));
+//! assert_eq!(String::from_utf16_lossy(caps.at(1).unwrap()), "örld");
+//! ```
+
+use std::iter::FusedIterator;
+
+use super::{
+    EncodedChars, Encoding, Error, ErrorKind, RegexBuilder, RegexOptions, Region, SearchOptions,
+    Syntax,
+};
+
+/// The UTF-16 encoding with the target's byte order.
+#[cfg(target_endian = "little")]
+const NATIVE_UTF16: Encoding = Encoding::Utf16Le;
+
+/// The UTF-16 encoding with the target's byte order.
+#[cfg(target_endian = "big")]
+const NATIVE_UTF16: Encoding = Encoding::Utf16Be;
+
+/// UTF-16 Buffer
+///
+/// Represents a buffer of native-endian UTF-16 code units. This can
+/// be used with the `*_with_encoding` methods on `onig::Regex` for
+/// regexes compiled from a UTF-16 pattern. Note that `EncodedChars`
+/// offsets and lengths are always measured in bytes.
+///
+/// The code units are checked when the buffer is created, so a
+/// buffer always holds valid UTF-16.
+#[derive(Debug, Clone, Copy)]
+pub struct EncodedUtf16<'a> {
+    units: &'a [u16],
+}
+
+impl<'a> EncodedUtf16<'a> {
+    /// New UTF-16 Buffer
+    ///
+    /// # Arguments
+    ///
+    ///  * `units` - The UTF-16 code units of the buffer
+    ///
+    /// # Returns
+    ///
+    /// A new buffer instance
+    ///
+    /// # Panics
+    ///
+    /// If `units` contains an unpaired surrogate.
+    pub fn new(units: &'a [u16]) -> EncodedUtf16<'a> {
+        check_utf16(units);
+        EncodedUtf16::unchecked(units)
+    }
+
+    /// New Validated Buffer
+    ///
+    /// Creates a buffer as `EncodedUtf16::new` does, but returns an
+    /// `Error` with the kind `ErrorKind::InvalidEncoding` rather than
+    /// panicking if `units` contains an unpaired surrogate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use onig::{EncodedUtf16, ErrorKind};
+    ///
+    /// assert!(EncodedUtf16::try_new(&[0x68, 0x69]).is_ok());
+    /// let e = EncodedUtf16::try_new(&[0x68, 0xD800]).unwrap_err();
+    /// assert_eq!(e.kind(), ErrorKind::InvalidEncoding);
+    /// ```
+    pub fn try_new(units: &'a [u16]) -> Result<EncodedUtf16<'a>, Error> {
+        if is_utf16(units) {
+            Ok(EncodedUtf16::unchecked(units))
+        } else {
+            Err(Error::custom(
+                ErrorKind::InvalidEncoding,
+                "Buffer is not valid UTF-16",
+            ))
+        }
+    }
+
+    /// Create a buffer from `units` which are already known to be
+    /// valid UTF-16.
+    fn unchecked(units: &'a [u16]) -> EncodedUtf16<'a> {
+        EncodedUtf16 { units }
+    }
+}
+
+impl<'a> EncodedChars for EncodedUtf16<'a> {
+    fn start_ptr(&self) -> *const onig_sys::OnigUChar {
+        self.units.as_ptr() as *const onig_sys::OnigUChar
+    }
+
+    fn limit_ptr(&self) -> *const onig_sys::OnigUChar {
+        self.units[self.units.len()..].as_ptr() as *const onig_sys::OnigUChar
+    }
+
+    fn encoding(&self) -> onig_sys::OnigEncoding {
+        NATIVE_UTF16.as_raw()
+    }
+
+    fn len(&self) -> usize {
+        self.units.len() * 2
+    }
+}
+
+/// A UTF-16 Regex
+///
+/// Wraps an `onig::Regex` compiled with the native-endian UTF-16
+/// encoding so that it can search `&[u16]` buffers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Regex {
+    inner: super::Regex,
+}
+
+impl Regex {
+    /// Create a Regex
+    ///
+    /// Transcodes `pattern` to UTF-16 and compiles it with the default
+    /// options and syntax.
+    pub fn new(pattern: &str) -> Result<Regex, Error> {
+        let units = pattern.encode_utf16().collect::<Vec<_>>();
+        RegexBuilder::with_encoding(EncodedUtf16::unchecked(&units))
+            .build()
+            .map(|inner| Regex { inner })
+    }
+
+    /// Create a Regex, Specifying Options
+    ///
+    /// Transcodes `pattern` to UTF-16 and compiles it with the given
+    /// options and syntax.
+    pub fn with_options(
+        pattern: &str,
+        option: RegexOptions,
+        syntax: &Syntax,
+    ) -> Result<Regex, Error> {
+        let units = pattern.encode_utf16().collect::<Vec<_>>();
+        super::Regex::with_options_and_encoding(EncodedUtf16::unchecked(&units), option, syntax)
+            .map(|inner| Regex { inner })
+    }
+
+    /// Get the Underlying Regex
+    pub fn as_regex(&self) -> &super::Regex {
+        &self.inner
+    }
+
+    /// Get the Number of Capture Groups in this Pattern
+    pub fn captures_len(&self) -> usize {
+        self.inner.captures_len()
+    }
+
+    /// Returns true if and only if the regex matches the whole of
+    /// `text`.
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn is_match(&self, text: &[u16]) -> bool {
+        check_utf16(text);
+        self.inner
+            .match_with_encoding(
+                EncodedUtf16::unchecked(text),
+                0,
+                SearchOptions::SEARCH_OPTION_WHOLE_STRING,
+                None,
+            )
+            .map(|r| r == text.len() * 2)
+            .unwrap_or(false)
+    }
+
+    /// Find a Match in a Buffer
+    ///
+    /// Returns the start and end of the first match of the regex
+    /// within `text`, in code units, or `None` if there is no match.
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn find(&self, text: &[u16]) -> Option<(usize, usize)> {
+        check_utf16(text);
+        self.inner
+            .find_with_encoding(EncodedUtf16::unchecked(text))
+            .map(|(beg, end)| (beg / 2, end / 2))
+    }
+
+    /// Search pattern in Buffer
+    ///
+    /// Search for the regex within `text` between the code unit
+    /// offsets `from` and `to`. Returns the code unit offset of the
+    /// start of the match.
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn search_with_options(
+        &self,
+        text: &[u16],
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+    ) -> Option<usize> {
+        check_utf16(text);
+        self.search_region(text, from, to, options, None)
+    }
+
+    /// Search `text`, which has already been checked, filling in
+    /// `region` with byte offsets.
+    fn search_region(
+        &self,
+        text: &[u16],
+        from: usize,
+        to: usize,
+        options: SearchOptions,
+        region: Option<&mut Region>,
+    ) -> Option<usize> {
+        self.inner
+            .search_with_encoding(
+                EncodedUtf16::unchecked(text),
+                from * 2,
+                to * 2,
+                options,
+                region,
+            )
+            .map(|pos| pos / 2)
+    }
+
+    /// Returns the capture groups corresponding to the leftmost-first match
+    /// in text. Capture group `0` always corresponds to the entire match.
+    /// If no match is found, then `None` is returned.
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn captures<'t>(&self, text: &'t [u16]) -> Option<Captures<'t>> {
+        check_utf16(text);
+        let mut region = Region::new();
+        self.search_region(
+            text,
+            0,
+            text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )
+        .map(|pos| Captures {
+            text,
+            region,
+            offset: pos,
+        })
+    }
+
+    /// Returns an iterator for each successive non-overlapping match in
+    /// `text`, returning the start and end code unit indices with
+    /// respect to `text`.
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn find_iter<'r, 't>(&'r self, text: &'t [u16]) -> FindMatches<'r, 't> {
+        FindMatches {
+            captures: self.captures_iter(text),
+        }
+    }
+
+    /// Returns an iterator over all the non-overlapping capture groups matched
+    /// in `text`. This is operationally the same as `find_iter` (except it
+    /// yields information about submatches).
+    ///
+    /// # Panics
+    ///
+    /// If `text` contains an unpaired surrogate.
+    pub fn captures_iter<'r, 't>(&'r self, text: &'t [u16]) -> FindCaptures<'r, 't> {
+        check_utf16(text);
+        FindCaptures {
+            regex: self,
+            text,
+            last_end: 0,
+            last_match_end: None,
+        }
+    }
+}
+
+/// Panic if `text` isn't valid UTF-16. Oniguruma would read past the
+/// end of the buffer if it ended with an unpaired high surrogate.
+fn check_utf16(text: &[u16]) {
+    if !is_utf16(text) {
+        panic!("Onig: Regex search error: Buffer is not valid UTF-16");
+    }
+}
+
+fn is_utf16(text: &[u16]) -> bool {
+    char::decode_utf16(text.iter().cloned()).all(|c| c.is_ok())
+}
+
+/// Captures represents a group of captured UTF-16 slices for a single
+/// match.
+///
+/// The 0th capture always corresponds to the entire match. Positions
+/// returned from a capture group are always code unit indices.
+///
+/// `'t` is the lifetime of the matched text.
+#[derive(Debug)]
+pub struct Captures<'t> {
+    text: &'t [u16],
+    region: Region,
+    offset: usize,
+}
+
+impl<'t> Captures<'t> {
+    /// Returns the start and end positions of the Nth capture group. Returns
+    /// `None` if i is not a valid capture group or if the capture group did
+    /// not match anything. The positions returned are always code unit
+    /// indices with respect to the original buffer matched.
+    pub fn pos(&self, pos: usize) -> Option<(usize, usize)> {
+        self.region.pos(pos).map(|(beg, end)| (beg / 2, end / 2))
+    }
+
+    /// Returns the matched code units for the capture group `i`. If `i`
+    /// isn't a valid capture group or didn't match anything, then `None`
+    /// is returned.
+    pub fn at(&self, pos: usize) -> Option<&'t [u16]> {
+        self.pos(pos).map(|(beg, end)| &self.text[beg..end])
+    }
+
+    /// Returns the number of captured groups.
+    pub fn len(&self) -> usize {
+        self.region.len()
+    }
+
+    /// Returns true if and only if there are no captured groups.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Offset of the captures within the given buffer, in code units.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// An iterator over all non-overlapping matches for a particular buffer.
+///
+/// The iterator yields the start and end of each match in code units.
+///
+/// `'r` is the lifetime of the `Regex` struct and `'t` is the lifetime
+/// of the matched buffer.
+pub struct FindMatches<'r, 't> {
+    captures: FindCaptures<'r, 't>,
+}
+
+impl<'r, 't> Iterator for FindMatches<'r, 't> {
+    type Item = (usize, usize);
+
+    fn next(&mut self) -> Option<(usize, usize)> {
+        self.captures.next().and_then(|caps| caps.pos(0))
+    }
+}
+
+impl<'r, 't> FusedIterator for FindMatches<'r, 't> {}
+
+/// An iterator that yields all non-overlapping capture groups matching a
+/// particular regular expression.
+///
+/// `'r` is the lifetime of the `Regex` struct and `'t` is the lifetime
+/// of the matched buffer.
+pub struct FindCaptures<'r, 't> {
+    regex: &'r Regex,
+    text: &'t [u16],
+    last_end: usize,
+    last_match_end: Option<usize>,
+}
+
+impl<'r, 't> Iterator for FindCaptures<'r, 't> {
+    type Item = Captures<'t>;
+
+    fn next(&mut self) -> Option<Captures<'t>> {
+        if self.last_end > self.text.len() {
+            return None;
+        }
+
+        let mut region = Region::new();
+        let r = self.regex.search_region(
+            self.text,
+            self.last_end,
+            self.text.len(),
+            SearchOptions::SEARCH_OPTION_NONE,
+            Some(&mut region),
+        )?;
+        let (s, e) = region.pos(0).map(|(s, e)| (s / 2, e / 2)).unwrap();
+
+        // Don't accept empty matches immediately following the last match.
+        // i.e., no infinite loops please.
+        if e == s && self.last_match_end == Some(e) {
+            self.last_end += match self.text.get(self.last_end) {
+                Some(0xD800..=0xDBFF) if self.last_end + 1 < self.text.len() => 2,
+                _ => 1,
+            };
+            return self.next();
+        } else {
+            self.last_end = e;
+            self.last_match_end = Some(e);
+        }
+        Some(Captures {
+            text: self.text,
+            region,
+            offset: r,
+        })
+    }
+}
+
+impl<'r, 't> FusedIterator for FindCaptures<'r, 't> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(s: &str) -> Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn test_utf16_buffer() {
+        let text = utf16("hi");
+        let buff = EncodedUtf16::new(&text);
+        assert_eq!(buff.len(), 4);
+        assert_eq!(buff.limit_ptr() as usize - buff.start_ptr() as usize, 4);
+        assert_eq!(Encoding::from_raw(buff.encoding()), Some(NATIVE_UTF16));
+    }
+
+    #[test]
+    #[should_panic(expected = "Buffer is not valid UTF-16")]
+    fn test_utf16_buffer_unpaired_surrogate() {
+        EncodedUtf16::new(&[0x68, 0xD800]);
+    }
+
+    #[test]
+    fn test_utf16_with_onig_regex() {
+        let pattern = utf16("l+");
+        let regex = super::super::Regex::with_encoding(EncodedUtf16::new(&pattern)).unwrap();
+        let text = utf16("hello");
+        assert_eq!(
+            regex.find_with_encoding(EncodedUtf16::new(&text)),
+            Some((4, 8))
+        );
+    }
+
+    #[test]
+    fn test_utf16_find() {
+        let regex = Regex::new("wörld").unwrap();
+        assert_eq!(regex.find(&utf16("hello wörld")), Some((6, 11)));
+        assert_eq!(regex.find(&utf16("hello world")), None);
+    }
+
+    #[test]
+    fn test_utf16_is_match() {
+        let regex = Regex::new(r"\w+").unwrap();
+        assert!(regex.is_match(&utf16("héllo")));
+        assert!(!regex.is_match(&utf16("héllo wörld")));
+    }
+
+    #[test]
+    fn test_utf16_captures() {
+        let regex = Regex::new(r"(\d+)-(\d+)|(x)").unwrap();
+        let text = utf16("😀 10-20");
+        let caps = regex.captures(&text).unwrap();
+        assert_eq!(caps.len(), 4);
+        assert_eq!(caps.offset(), 3);
+        assert_eq!(caps.pos(0), Some((3, 8)));
+        assert_eq!(caps.at(2), Some(&utf16("20")[..]));
+        assert_eq!(caps.at(3), None);
+    }
+
+    #[test]
+    fn test_utf16_find_iter_zero_length() {
+        let regex = Regex::new(r"\d*").unwrap();
+        let ms = regex.find_iter(&utf16("😀1b")).collect::<Vec<_>>();
+        assert_eq!(ms, vec![(0, 0), (2, 3), (4, 4)]);
+    }
+
+    #[test]
+    fn test_utf16_search_range() {
+        let regex = Regex::new("a").unwrap();
+        let text = utf16("aaa");
+        let r = regex.search_with_options(&text, 1, 3, SearchOptions::SEARCH_OPTION_NONE);
+        assert_eq!(r, Some(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "not valid UTF-16")]
+    fn test_utf16_unpaired_surrogate() {
+        let regex = Regex::new("a").unwrap();
+        regex.find(&[0x61, 0xD800]);
+    }
+}
@@ -0,0 +1,141 @@
+//! Pattern Warnings
+//!
+//! Oniguruma can warn about suspicious constructs while compiling a
+//! pattern, such as an unescaped `[` in a character class or a
+//! redundant nested repeat like `(?:a*)*`. Which warnings are given
+//! depends on the `SYNTAX_BEHAVIOR_WARN_*` flags of the syntax.
+//!
+//! Warnings are discarded unless a handler is installed with
+//! `set_warning_handler`. When the `log` feature is enabled warnings
+//! with no handler are instead logged at the `warn` level with the
+//! `onig` target.
+
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::RwLock;
+
+use once_cell::sync::Lazy;
+
+use super::REGEX_NEW_MUTEX;
+
+type WarningFn = dyn Fn(&str) + Send + Sync;
+
+/// The installed warning handler, if any.
+static WARNING_HANDLER: Lazy<RwLock<Option<Box<WarningFn>>>> = Lazy::new(|| RwLock::new(None));
+
+/// Set the Warning Handler
+///
+/// Installs `handler` to receive the warnings Oniguruma gives while
+/// compiling patterns, replacing any previous handler. The handler is
+/// process-wide and is called with each warning message, which
+/// includes the offending pattern.
+///
+/// The handler is called while a regex is being compiled, so it must
+/// not compile regexes itself. If the handler panics the panic is
+/// discarded, as it can't unwind through Oniguruma.
+///
+/// # Examples
+///
+/// ```
+/// use onig::{Regex, RegexOptions, Syntax};
+/// use std::sync::{Arc, Mutex};
+///
+/// let warnings = Arc::new(Mutex::new(Vec::new()));
+/// let sink = warnings.clone();
+/// onig::set_warning_handler(move |message| sink.lock().unwrap().push(message.to_owned()));
+///
+/// Regex::with_options("(?:a*)*", RegexOptions::REGEX_OPTION_NONE, Syntax::ruby()).unwrap();
+/// onig::clear_warning_handler();
+///
+/// assert!(warnings
+///     .lock()
+///     .unwrap()
+///     .iter()
+///     .any(|w| w.contains("redundant nested repeat operator")));
+/// ```
+pub fn set_warning_handler<F>(handler: F)
+where
+    F: Fn(&str) + Send + Sync + 'static,
+{
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    *WARNING_HANDLER.write().unwrap() = Some(Box::new(handler));
+    unsafe { install(Some(warning_trampoline)) };
+}
+
+/// Clear the Warning Handler
+///
+/// Removes the handler installed by `set_warning_handler`. Later
+/// warnings are discarded, or logged if the `log` feature is enabled.
+pub fn clear_warning_handler() {
+    let _guard = REGEX_NEW_MUTEX.lock().unwrap();
+    *WARNING_HANDLER.write().unwrap() = None;
+    if cfg!(not(feature = "log")) {
+        unsafe { install(Some(onig_sys::onig_null_warn)) };
+    }
+}
+
+/// Route warnings to the `log` crate. Called with `REGEX_NEW_MUTEX`
+/// held before each regex is compiled.
+#[cfg(feature = "log")]
+pub(crate) fn ensure_logging() {
+    static INSTALLED: std::sync::Once = std::sync::Once::new();
+    INSTALLED.call_once(|| unsafe { install(Some(warning_trampoline)) });
+}
+
+unsafe fn install(func: onig_sys::OnigWarnFunc) {
+    onig_sys::onig_set_warn_func(func);
+    onig_sys::onig_set_verb_warn_func(func);
+}
+
+unsafe extern "C" fn warning_trampoline(message: *const c_char) {
+    if message.is_null() {
+        return;
+    }
+    let message = CStr::from_ptr(message).to_string_lossy();
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| {
+        match WARNING_HANDLER.read().unwrap().as_ref() {
+            Some(handler) => handler(&message),
+            None => log_warning(&message),
+        }
+    }));
+}
+
+#[cfg(feature = "log")]
+fn log_warning(message: &str) {
+    log::warn!(target: "onig", "{}", message);
+}
+
+#[cfg(not(feature = "log"))]
+fn log_warning(_message: &str) {}
+
+#[cfg(test)]
+mod tests {
+    use super::super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_warning_handler() {
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let sink = warnings.clone();
+        set_warning_handler(move |message| sink.lock().unwrap().push(message.to_owned()));
+
+        Regex::with_options("a]", RegexOptions::REGEX_OPTION_NONE, Syntax::ruby()).unwrap();
+        Regex::with_options("(?:b+)+", RegexOptions::REGEX_OPTION_NONE, Syntax::ruby()).unwrap();
+
+        // Other tests may compile patterns while the handler is set,
+        // so only look for the warnings from these ones.
+        let warnings = warnings.lock().unwrap();
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("regular expression has ']' without escape")));
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("redundant nested repeat operator") && w.contains("(?:b+)+")));
+
+        // A panicking handler doesn't stop the pattern compiling.
+        set_warning_handler(|_| panic!("warning handler panicked"));
+        Regex::with_options("(?:c+)+", RegexOptions::REGEX_OPTION_NONE, Syntax::ruby()).unwrap();
+        clear_warning_handler();
+    }
+}
@@ -0,0 +1,333 @@
+//! Compile Time Checked Regexes
+//!
+//! This crate provides the `regex!` macro for the `onig` crate. Enable
+//! it with the `macros` feature of `onig` rather than depending on
+//! this crate directly.
+//!
+//! The pattern is compiled with the bundled Oniguruma when the crate
+//! using the macro is built, so an invalid pattern is a compile error
+//! rather than a panic at runtime.
+
+use std::os::raw::c_int;
+use std::ptr::null_mut;
+use std::str;
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{parse_macro_input, Ident, LitStr, Token};
+
+/// The options `regex!` accepts, with their Oniguruma values.
+const OPTIONS: &[(&str, onig_sys::OnigOptionType)] = &[
+    ("REGEX_OPTION_NONE", onig_sys::ONIG_OPTION_NONE),
+    ("REGEX_OPTION_IGNORECASE", onig_sys::ONIG_OPTION_IGNORECASE),
+    ("REGEX_OPTION_EXTEND", onig_sys::ONIG_OPTION_EXTEND),
+    ("REGEX_OPTION_MULTILINE", onig_sys::ONIG_OPTION_MULTILINE),
+    ("REGEX_OPTION_SINGLELINE", onig_sys::ONIG_OPTION_SINGLELINE),
+    (
+        "REGEX_OPTION_FIND_LONGEST",
+        onig_sys::ONIG_OPTION_FIND_LONGEST,
+    ),
+    (
+        "REGEX_OPTION_FIND_NOT_EMPTY",
+        onig_sys::ONIG_OPTION_FIND_NOT_EMPTY,
+    ),
+    (
+        "REGEX_OPTION_NEGATE_SINGLELINE",
+        onig_sys::ONIG_OPTION_NEGATE_SINGLELINE,
+    ),
+    (
+        "REGEX_OPTION_DONT_CAPTURE_GROUP",
+        onig_sys::ONIG_OPTION_DONT_CAPTURE_GROUP,
+    ),
+    (
+        "REGEX_OPTION_CAPTURE_GROUP",
+        onig_sys::ONIG_OPTION_CAPTURE_GROUP,
+    ),
+    (
+        "REGEX_OPTION_IGNORECASE_IS_ASCII",
+        onig_sys::ONIG_OPTION_IGNORECASE_IS_ASCII,
+    ),
+    (
+        "REGEX_OPTION_WORD_IS_ASCII",
+        onig_sys::ONIG_OPTION_WORD_IS_ASCII,
+    ),
+    (
+        "REGEX_OPTION_DIGIT_IS_ASCII",
+        onig_sys::ONIG_OPTION_DIGIT_IS_ASCII,
+    ),
+    (
+        "REGEX_OPTION_SPACE_IS_ASCII",
+        onig_sys::ONIG_OPTION_SPACE_IS_ASCII,
+    ),
+    (
+        "REGEX_OPTION_POSIX_IS_ASCII",
+        onig_sys::ONIG_OPTION_POSIX_IS_ASCII,
+    ),
+    (
+        "REGEX_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER",
+        onig_sys::ONIG_OPTION_TEXT_SEGMENT_EXTENDED_GRAPHEME_CLUSTER,
+    ),
+    (
+        "REGEX_OPTION_TEXT_SEGMENT_WORD",
+        onig_sys::ONIG_OPTION_TEXT_SEGMENT_WORD,
+    ),
+];
+
+/// The syntaxes `regex!` accepts. Each is the name of a constructor
+/// on `onig::Syntax`.
+const SYNTAXES: &[&str] = &[
+    "asis",
+    "emacs",
+    "gnu_regex",
+    "grep",
+    "java",
+    "oniguruma",
+    "perl",
+    "perl_ng",
+    "posix_basic",
+    "posix_extended",
+    "python",
+    "ruby",
+];
+
+/// Compile Time Checked Regex
+///
+/// Expands to a `&'static onig::Regex` which is compiled with
+/// `Regex::with_options` the first time the expression is evaluated.
+/// The pattern is checked when the calling crate is built, so an
+/// invalid pattern is reported as a compile error.
+///
+/// Options and syntax can be given after the pattern. Options are
+/// names of `onig::RegexOptions` flags joined with `|`, and the syntax
+/// is the name of one of the `onig::Syntax` constructors. By default
+/// no options and `Syntax::oniguruma()` are used. The syntax is fixed
+/// when the macro is expanded, so changes to `onig::defaults` don't
+/// affect it.
+///
+/// # Examples
+///
+/// ```
+/// use onig::regex;
+///
+/// fn is_greeting(text: &str) -> bool {
+///     regex!(r"^(hello|hi)\b", options = REGEX_OPTION_IGNORECASE).find(text).is_some()
+/// }
+///
+/// assert!(is_greeting("Hello, world"));
+/// assert!(!is_greeting("goodbye"));
+///
+/// let words = regex!(r"(?<word>\w+)", syntax = perl_ng);
+/// assert_eq!(words.find_iter("two words").count(), 2);
+/// ```
+///
+/// Invalid patterns don't compile:
+///
+/// ```compile_fail
+/// let r = onig::regex!("unclosed (group");
+/// ```
+#[proc_macro]
+pub fn regex(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as RegexInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// The parsed arguments of a `regex!` invocation.
+struct RegexInput {
+    pattern: LitStr,
+    options: Vec<Ident>,
+    syntax: Option<Ident>,
+}
+
+impl Parse for RegexInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut parsed = RegexInput {
+            pattern: input.parse()?,
+            options: Vec::new(),
+            syntax: None,
+        };
+
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "options" => {
+                    let options = Punctuated::<Ident, Token![|]>::parse_separated_nonempty(input)?;
+                    parsed.options.extend(options);
+                }
+                "syntax" => parsed.syntax = Some(input.parse()?),
+                _ => {
+                    return Err(syn::Error::new(
+                        key.span(),
+                        "expected `options` or `syntax`",
+                    ))
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+}
+
+fn expand(input: RegexInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut option_bits = onig_sys::ONIG_OPTION_NONE;
+    for option in &input.options {
+        option_bits |= option_value(option)?;
+    }
+    let syntax = match &input.syntax {
+        Some(syntax) if SYNTAXES.contains(&syntax.to_string().as_str()) => syntax.clone(),
+        Some(syntax) => {
+            return Err(syn::Error::new(
+                syntax.span(),
+                format!("unknown syntax, expected one of: {}", SYNTAXES.join(", ")),
+            ))
+        }
+        None => Ident::new("oniguruma", Span::call_site()),
+    };
+
+    if let Err(message) = check_pattern(&input.pattern.value(), option_bits, &syntax.to_string()) {
+        return Err(syn::Error::new(
+            input.pattern.span(),
+            format!("invalid regex: {}", message),
+        ));
+    }
+
+    let pattern = &input.pattern;
+    let options = if input.options.is_empty() {
+        quote!(::onig::RegexOptions::REGEX_OPTION_NONE)
+    } else {
+        let options = &input.options;
+        quote!(#(::onig::RegexOptions::#options)|*)
+    };
+    Ok(quote! {{
+        static REGEX: ::onig::__private::Lazy<::onig::Regex> =
+            ::onig::__private::Lazy::new(|| {
+                ::onig::Regex::with_options(#pattern, #options, ::onig::Syntax::#syntax())
+                    .expect("pattern was checked at compile time")
+            });
+        &*REGEX
+    }})
+}
+
+fn option_value(option: &Ident) -> syn::Result<onig_sys::OnigOptionType> {
+    let name = option.to_string();
+    OPTIONS
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|&(_, value)| value)
+        .ok_or_else(|| syn::Error::new(option.span(), format!("unknown regex option `{}`", name)))
+}
+
+/// Compile `pattern` with Oniguruma, returning the error message if
+/// it is invalid.
+#[allow(unknown_lints, unused_unsafe, static_mut_refs)]
+fn check_pattern(
+    pattern: &str,
+    options: onig_sys::OnigOptionType,
+    syntax: &str,
+) -> Result<(), String> {
+    let syntax: *mut onig_sys::OnigSyntaxType = unsafe {
+        match syntax {
+            "asis" => &mut onig_sys::OnigSyntaxASIS as *mut _,
+            "emacs" => &mut onig_sys::OnigSyntaxEmacs as *mut _,
+            "gnu_regex" => &mut onig_sys::OnigSyntaxGnuRegex as *mut _,
+            "grep" => &mut onig_sys::OnigSyntaxGrep as *mut _,
+            "java" => &mut onig_sys::OnigSyntaxJava as *mut _,
+            "oniguruma" => &mut onig_sys::OnigSyntaxOniguruma as *mut _,
+            "perl" => &mut onig_sys::OnigSyntaxPerl as *mut _,
+            "perl_ng" => &mut onig_sys::OnigSyntaxPerl_NG as *mut _,
+            "posix_basic" => &mut onig_sys::OnigSyntaxPosixBasic as *mut _,
+            "posix_extended" => &mut onig_sys::OnigSyntaxPosixExtended as *mut _,
+            "python" => &mut onig_sys::OnigSyntaxPython as *mut _,
+            "ruby" => &mut onig_sys::OnigSyntaxRuby as *mut _,
+            _ => return Err(format!("unknown syntax `{}`", syntax)),
+        }
+    };
+
+    let mut reg: onig_sys::OnigRegex = null_mut();
+    let mut error = onig_sys::OnigErrorInfo {
+        enc: null_mut(),
+        par: null_mut(),
+        par_end: null_mut(),
+    };
+    let err = unsafe {
+        onig_sys::onig_new(
+            &mut reg,
+            pattern.as_ptr(),
+            pattern.as_ptr().add(pattern.len()),
+            options,
+            &mut onig_sys::OnigEncodingUTF8 as *mut _,
+            syntax,
+            &mut error,
+        )
+    };
+    if err == onig_sys::ONIG_NORMAL as c_int {
+        unsafe { onig_sys::onig_free(reg) };
+        return Ok(());
+    }
+
+    let mut buff = [0; onig_sys::ONIG_MAX_ERROR_MESSAGE_LEN as usize];
+    let len = unsafe { onig_sys::onig_error_code_to_str(buff.as_mut_ptr(), err, &error) };
+    Err(str::from_utf8(&buff[..len as usize])
+        .unwrap_or("unknown error")
+        .to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_pattern() {
+        assert!(check_pattern(r"hello (\w+)", onig_sys::ONIG_OPTION_NONE, "oniguruma").is_ok());
+        assert_eq!(
+            check_pattern("a(b", onig_sys::ONIG_OPTION_NONE, "oniguruma"),
+            Err("end pattern with unmatched parenthesis".to_owned())
+        );
+        assert_eq!(
+            check_pattern(r"\k<nope>", onig_sys::ONIG_OPTION_NONE, "ruby"),
+            Err("undefined name <nope> reference".to_owned())
+        );
+    }
+
+    #[test]
+    fn test_check_pattern_syntax() {
+        // `(?P<name>...)` is only valid in some syntaxes.
+        assert!(check_pattern("(?P<n>a)", onig_sys::ONIG_OPTION_NONE, "python").is_ok());
+        assert!(check_pattern("(?P<n>a)", onig_sys::ONIG_OPTION_NONE, "ruby").is_err());
+    }
+
+    #[test]
+    fn test_parse_input() {
+        let input: RegexInput = syn::parse_str(
+            r#""a+", options = REGEX_OPTION_IGNORECASE | REGEX_OPTION_EXTEND, syntax = perl,"#,
+        )
+        .unwrap();
+        assert_eq!(input.pattern.value(), "a+");
+        assert_eq!(input.options.len(), 2);
+        assert_eq!(input.syntax.unwrap(), "perl");
+
+        assert!(syn::parse_str::<RegexInput>(r#""a", flags = X"#).is_err());
+        let input: RegexInput = syn::parse_str(r#""a", options = REGEX_OPTION_BOGUS"#).unwrap();
+        assert!(expand(input).is_err());
+    }
+
+    #[test]
+    fn test_expand_fixed_syntax() {
+        let input: RegexInput = syn::parse_str(r#""a+""#).unwrap();
+        let expanded = expand(input).unwrap().to_string();
+        assert!(expanded.contains("Syntax :: oniguruma ()"));
+        assert!(!expanded.contains("default"));
+
+        let input: RegexInput = syn::parse_str(r#""a+", syntax = default"#).unwrap();
+        assert!(expand(input).is_err());
+    }
+}
@@ -74,6 +74,12 @@ fn compile() {
         cc.define("ONIG_DEBUG_MATCH", Some("1"));
     }
 
+    // Needed for `ONIGENC_CASE_FOLD_TURKISH_AZERI` to have any effect.
+    cc.define("USE_UNICODE_CASE_FOLD_TURKISH_AZERI", Some("1"));
+    // Lets `onig` know, through `DEP_ONIG_BUNDLED`, that it can rely
+    // on the bundled build options.
+    println!("cargo:bundled=1");
+
     if !src.exists() {
         panic!(
             "Unable to find source files in {}. Is oniguruma submodule checked out?\n\